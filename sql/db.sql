@@ -13,9 +13,69 @@ CREATE TABLE IF NOT EXISTS schedules (
 	next_at bigint NULL,
 	repeat bigint NULL,
 	until bigint NULL,
-	inactive boolean NOT NULL DEFAULT FALSE
+	inactive boolean NOT NULL DEFAULT FALSE,
+	jitter int NULL,
+	anchor bigint NULL,
+	tenant_id varchar(64) NOT NULL DEFAULT 'default'
 );
 
+-- Rows created before tenants belong to the default tenant
+ALTER TABLE schedules ADD COLUMN IF NOT EXISTS tenant_id varchar(64) NOT NULL DEFAULT 'default';
+ALTER TABLE schedules ADD COLUMN IF NOT EXISTS anchor bigint NULL;
+ALTER TABLE schedules ADD COLUMN IF NOT EXISTS jitter int NULL;
+
+CREATE INDEX IF NOT EXISTS ix_schedules_tenant_id ON schedules
+	USING btree (tenant_id, schedule_id);
+
+CREATE TABLE IF NOT EXISTS subscriptions (
+	subscription_id varchar(64) PRIMARY KEY,
+	url varchar(2048) NOT NULL,
+	events text[] NOT NULL,
+	filter jsonb NOT NULL DEFAULT '{}',
+	secret varchar(128) NOT NULL,
+	paused boolean NOT NULL DEFAULT FALSE,
+	created_at timestamptz NOT NULL DEFAULT NOW(),
+	tenant_id varchar(64) NOT NULL DEFAULT 'default'
+);
+
+ALTER TABLE subscriptions ADD COLUMN IF NOT EXISTS tenant_id varchar(64) NOT NULL DEFAULT 'default';
+
+CREATE TABLE IF NOT EXISTS api_keys (
+	key_id varchar(64) PRIMARY KEY,
+	name varchar(256) NOT NULL,
+	prefix varchar(16) NOT NULL,
+	key_hash char(64) NOT NULL UNIQUE,
+	scopes text[] NOT NULL,
+	created_at timestamptz NOT NULL DEFAULT NOW(),
+	last_used_at timestamptz NULL,
+	revoked_at timestamptz NULL,
+	tenant_id varchar(64) NOT NULL DEFAULT 'default'
+);
+
+ALTER TABLE api_keys ADD COLUMN IF NOT EXISTS tenant_id varchar(64) NOT NULL DEFAULT 'default';
+
+CREATE TABLE IF NOT EXISTS quotas (
+	tenant_id varchar(64) PRIMARY KEY,
+	jobs_per_day bigint NULL,
+	pending_jobs bigint NULL,
+	schedules bigint NULL,
+	max_body bigint NULL,
+	max_retries int NULL,
+	updated_at timestamptz NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS secrets (
+	tenant_id varchar(64) NOT NULL,
+	name varchar(64) NOT NULL,
+	value BYTEA NOT NULL,
+	envelope jsonb NOT NULL,
+	hosts text[] NOT NULL DEFAULT '{}',
+	created_at timestamptz NOT NULL DEFAULT NOW(),
+	updated_at timestamptz NOT NULL DEFAULT NOW(),
+	PRIMARY KEY (tenant_id, name)
+);
+ALTER TABLE secrets ADD COLUMN IF NOT EXISTS hosts text[] NOT NULL DEFAULT '{}';
+
 CREATE TABLE IF NOT EXISTS jobs (
 	id bigint PRIMARY KEY GENERATED BY DEFAULT AS IDENTITY,
 	created_at timestamptz NOT NULL DEFAULT NOW(),
@@ -23,13 +83,56 @@ CREATE TABLE IF NOT EXISTS jobs (
 	headers jsonb NULL,
 	body BYTEA NULL,
 	schedule_id varchar(64) NULL REFERENCES schedules (schedule_id) MATCH SIMPLE,
-	external_id varchar(64) NULL
+	external_id varchar(64) NULL,
+	external_id_until bigint NULL,
+	body_ref varchar(64) NULL,
+	body_codec varchar(8) NULL,
+	envelope jsonb NULL,
+	tenant_id varchar(64) NOT NULL DEFAULT 'default'
 );
 
-CREATE UNIQUE INDEX IF NOT EXISTS ix_jobs_external_id ON jobs
-	USING btree (external_id)
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS tenant_id varchar(64) NOT NULL DEFAULT 'default';
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS envelope jsonb NULL;
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS body_codec varchar(8) NULL;
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS body_ref varchar(64) NULL;
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS external_id_until bigint NULL;
+
+-- Destination host of the search filter
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS host varchar(255)
+	GENERATED ALWAYS AS (lower(substring(meta->>'url' from '^[a-zA-Z][a-zA-Z0-9+.-]*://(?:[^/?#@]*@)?([^/:?#]+)'))) STORED;
+
+CREATE INDEX IF NOT EXISTS ix_jobs_tenant_id_host ON jobs
+	USING btree (tenant_id, host, id DESC)
+	WHERE host IS NOT NULL;
+
+-- Was unique before external ids could be reused after their dedup window
+DROP INDEX IF EXISTS ix_jobs_external_id;
+
+CREATE INDEX IF NOT EXISTS ix_jobs_tenant_id_external_id ON jobs
+	USING btree (tenant_id, external_id, id DESC)
     WHERE external_id IS NOT NULL;
 
+CREATE INDEX IF NOT EXISTS ix_jobs_tenant_id ON jobs
+	USING btree (tenant_id, id DESC);
+
+CREATE INDEX IF NOT EXISTS ix_jobs_external_id_pattern ON jobs
+	USING btree (tenant_id, external_id varchar_pattern_ops)
+	WHERE external_id IS NOT NULL;
+
+CREATE INDEX IF NOT EXISTS ix_jobs_schedule_id ON jobs
+	USING btree (schedule_id)
+	WHERE schedule_id IS NOT NULL;
+
+CREATE INDEX IF NOT EXISTS ix_jobs_body_ref ON jobs
+	USING btree (body_ref)
+	WHERE body_ref IS NOT NULL;
+
+CREATE INDEX IF NOT EXISTS ix_jobs_created_at ON jobs
+	USING btree (created_at);
+
+CREATE INDEX IF NOT EXISTS ix_jobs_tenant_id_created_at ON jobs
+	USING btree (tenant_id, created_at);
+
 CREATE TABLE IF NOT EXISTS scheduled (
 	id bigint NOT NULL PRIMARY KEY REFERENCES jobs(id) MATCH SIMPLE ON UPDATE NO ACTION ON DELETE CASCADE,
 	at bigint NOT NULL,
@@ -54,9 +157,37 @@ CREATE TABLE IF NOT EXISTS processed (
 	status processed_status NOT NULL,
 	meta jsonb NOT NULL,
 	headers jsonb NULL,
-	body BYTEA NULL
+	body BYTEA NULL,
+	body_ref varchar(64) NULL,
+	body_codec varchar(8) NULL,
+	envelope jsonb NULL,
+	tenant_id varchar(64) NOT NULL
 );
 
+-- Rows written before tenants get the tenant of their job
+DO $$
+BEGIN
+	IF NOT EXISTS (SELECT 1 FROM information_schema.columns WHERE table_name = 'processed' AND column_name = 'tenant_id') THEN
+		ALTER TABLE processed ADD COLUMN tenant_id varchar(64) NULL;
+		UPDATE processed t SET tenant_id = j.tenant_id FROM jobs j WHERE j.id = t.id;
+		ALTER TABLE processed ALTER COLUMN tenant_id SET NOT NULL;
+	END IF;
+END $$;
+
+ALTER TABLE processed ADD COLUMN IF NOT EXISTS envelope jsonb NULL;
+ALTER TABLE processed ADD COLUMN IF NOT EXISTS body_codec varchar(8) NULL;
+ALTER TABLE processed ADD COLUMN IF NOT EXISTS body_ref varchar(64) NULL;
+
+CREATE INDEX IF NOT EXISTS ix_processed_tenant_id_at ON processed
+	USING btree (tenant_id, at);
+
+CREATE INDEX IF NOT EXISTS ix_processed_tenant_id_status ON processed
+	USING btree (tenant_id, status, id DESC);
+
+CREATE INDEX IF NOT EXISTS ix_processed_body_ref ON processed
+	USING btree (body_ref)
+	WHERE body_ref IS NOT NULL;
+
 CREATE TYPE history_status AS ENUM (
 	'scheduled',
 	'enqueued',
@@ -73,13 +204,70 @@ CREATE TABLE IF NOT EXISTS history (
 	instance_id varchar(64) NOT NULL,
 	at timestamptz NOT NULL DEFAULT NOW(),
 	status history_status NOT NULL,
-	message text NULL
+	message text NULL,
+	tenant_id varchar(64) NOT NULL
 );
 
+-- Rows written before tenants get the tenant of their job
+DO $$
+BEGIN
+	IF NOT EXISTS (SELECT 1 FROM information_schema.columns WHERE table_name = 'history' AND column_name = 'tenant_id') THEN
+		ALTER TABLE history ADD COLUMN tenant_id varchar(64) NULL;
+		UPDATE history t SET tenant_id = j.tenant_id FROM jobs j WHERE j.id = t.id;
+		ALTER TABLE history ALTER COLUMN tenant_id SET NOT NULL;
+	END IF;
+END $$;
+
+CREATE INDEX IF NOT EXISTS ix_history_tenant_id_at ON history
+	USING btree (tenant_id, at)
+	WHERE status = 'enqueued' AND retry = 0;
+
 CREATE INDEX IF NOT EXISTS ix_enqueued_retry_id ON enqueued
 	USING btree (retry ASC NULLS LAST, id ASC NULLS LAST)
 	WHERE lock_at IS NULL;
 
+-- Events of subscribed jobs wait here until their delivery jobs are enqueued
+CREATE TABLE IF NOT EXISTS subscription_outbox (
+	seq bigserial PRIMARY KEY,
+	event jsonb NOT NULL
+);
+
+CREATE OR REPLACE FUNCTION history_notify() RETURNS trigger AS $$
+DECLARE
+	event jsonb;
+BEGIN
+	SELECT jsonb_build_object(
+		'id', NEW.id,
+		'retry', NEW.retry,
+		'instance_id', NEW.instance_id,
+		'at', NEW.at,
+		'status', NEW.status,
+		'message', NEW.message,
+		'schedule_id', j.schedule_id,
+		'external_id', j.external_id,
+		'subscription_id', j.meta->>'subscription_id',
+		'tenant_id', j.tenant_id
+	) INTO event
+	FROM jobs j WHERE j.id = NEW.id;
+	IF event IS NULL THEN
+		RETURN NULL;
+	END IF;
+	PERFORM pg_notify('irisqo_history', event::text);
+	-- Deliveries are jobs too, their events are never delivered
+	IF event->>'subscription_id' IS NULL AND EXISTS (
+		SELECT 1 FROM subscriptions s
+		WHERE NOT s.paused AND s.tenant_id = event->>'tenant_id'
+			AND s.events && ARRAY[NEW.status::text, CASE WHEN NEW.message = 'dead_lettered' THEN NEW.message END]
+	) THEN
+		INSERT INTO subscription_outbox(event) VALUES (event);
+	END IF;
+	RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE TRIGGER tr_history_notify AFTER INSERT ON history
+	FOR EACH ROW EXECUTE FUNCTION history_notify();
+
 
 -- select 'retried' as name, count(*) as count from public.scheduled where is_retried
 -- union all
@@ -0,0 +1,16 @@
+use crate::models::Error;
+use sqlx::{Pool, Postgres};
+
+/// Keys still referenced by `body_ref` of a job or a result
+pub async fn referenced(pool: &Pool<Postgres>, keys: &[String]) -> Result<Vec<String>, Error> {
+    const SQL: &str = "
+    SELECT body_ref FROM jobs WHERE body_ref = ANY($1)
+    UNION
+    SELECT body_ref FROM processed WHERE body_ref = ANY($1)
+    ";
+    let res = sqlx::query_scalar::<_, String>(SQL)
+        .bind(keys)
+        .fetch_all(pool)
+        .await?;
+    Ok(res)
+}
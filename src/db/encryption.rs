@@ -0,0 +1,82 @@
+use crate::models::{Envelope, Error};
+use sqlx::{Pool, Postgres, types::Json};
+use std::collections::HashMap;
+
+/// Tables with sealed bodies and headers
+#[derive(Debug, Clone, Copy)]
+pub enum SealedTable {
+    Jobs,
+    Processed,
+}
+
+impl SealedTable {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SealedTable::Jobs => "jobs",
+            SealedTable::Processed => "processed",
+        }
+    }
+}
+
+#[derive(Debug, sqlx::FromRow)]
+pub struct SealedRow {
+    pub id: i64,
+    #[sqlx(json(nullable))]
+    pub headers: Option<HashMap<String, String>>,
+    pub body: Option<Vec<u8>>,
+    pub body_ref: Option<String>,
+    #[sqlx(json(nullable))]
+    pub envelope: Option<Envelope>,
+}
+
+/// Rows after `after` in plaintext or sealed by another key than `key_id`
+pub async fn get_to_reencrypt(
+    pool: &Pool<Postgres>,
+    table: SealedTable,
+    key_id: &str,
+    after: i64,
+    limit: i64,
+) -> Result<Vec<SealedRow>, Error> {
+    let sql = format!(
+        "SELECT id, headers, body, body_ref, envelope FROM {}
+        WHERE id > $1
+        AND (
+            (envelope IS NULL AND (body IS NOT NULL OR body_ref IS NOT NULL OR (headers IS NOT NULL AND headers <> 'null'::jsonb)))
+            OR envelope->>'key_id' <> $2
+        )
+        ORDER BY id
+        LIMIT $3",
+        table.name()
+    );
+    let rows = sqlx::query_as::<_, SealedRow>(&sql)
+        .bind(after)
+        .bind(key_id)
+        .bind(limit)
+        .fetch_all(pool)
+        .await?;
+    Ok(rows)
+}
+
+/// Stores a row sealed again, skipped when its envelope changed since it was read
+pub async fn update_sealed(
+    pool: &Pool<Postgres>,
+    table: SealedTable,
+    row: &SealedRow,
+    previous: Option<&Envelope>,
+) -> Result<u64, Error> {
+    let sql = format!(
+        "UPDATE {} SET headers = $2, body = $3, body_ref = $4, envelope = $5
+        WHERE id = $1 AND envelope IS NOT DISTINCT FROM $6",
+        table.name()
+    );
+    let res = sqlx::query(&sql)
+        .bind(row.id)
+        .bind(Json(&row.headers))
+        .bind(&row.body)
+        .bind(&row.body_ref)
+        .bind(row.envelope.as_ref().map(Json))
+        .bind(previous.map(Json))
+        .execute(pool)
+        .await?;
+    Ok(res.rows_affected())
+}
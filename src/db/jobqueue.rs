@@ -1,46 +1,280 @@
-use crate::features::schedules::JobSchedule;
-use crate::models::{Error, JobCreateRow, JobEntry, JobWithRetry};
-use crate::models::{JobCreate, JobRow};
+use crate::features::{
+    results::{self, CancelOutcome},
+    schedules::{self, JobSchedule},
+};
+use crate::models::{Envelope, JobCreate, JobMeta, JobRow};
+use crate::models::{
+    Error, IdConflict, JobCreateRow, JobEntry, JobListRow, JobSearch, JobWithRetry,
+};
 use futures::stream::BoxStream;
-use sqlx::{Pool, Postgres, types::Json};
+use sqlx::{Connection, PgConnection, PgExecutor, Pool, Postgres, types::Json};
+use std::collections::HashMap;
 
 pub async fn create(
     pool: &Pool<Postgres>,
+    mut job: JobCreate,
+    tenant_id: &str,
+    instance_id: &str,
+) -> Result<JobCreateRow, Error> {
+    apply_jitter(pool, &mut job).await?;
+    if job.external_id.is_none() {
+        return create_job(pool, job, tenant_id, instance_id).await;
+    }
+    let mut tx = pool.begin().await?;
+    let res = create_deduped(&mut tx, job, tenant_id, instance_id).await?;
+    tx.commit().await?;
+    Ok(res)
+}
+
+/// Creates a job within the transaction of the caller
+pub async fn create_in(
+    conn: &mut PgConnection,
+    mut job: JobCreate,
+    tenant_id: &str,
+    instance_id: &str,
+) -> Result<JobCreateRow, Error> {
+    apply_jitter(&mut *conn, &mut job).await?;
+    create_deduped(conn, job, tenant_id, instance_id).await
+}
+
+/// Creates jobs in a single transaction, results are in the order of `jobs`.
+/// Plain jobs are inserted at once, scheduled and deduplicated jobs one by one.
+/// Without `atomic` every item runs in a savepoint and fails on its own,
+/// with `atomic` any error rolls back the whole batch.
+pub async fn create_batch(
+    pool: &Pool<Postgres>,
+    jobs: Vec<JobCreate>,
+    tenant_id: &str,
+    instance_id: &str,
+    atomic: bool,
+) -> Result<Vec<Result<JobCreateRow, Error>>, Error> {
+    let mut results: Vec<Option<Result<JobCreateRow, Error>>> = jobs.iter().map(|_| None).collect();
+    let mut plain = Vec::new();
+    let mut tx = pool.begin().await?;
+    for (idx, mut job) in jobs.into_iter().enumerate() {
+        let is_plain = job.schedule.is_none() && job.external_id.is_none();
+        if is_plain && job.meta.jitter.is_none() {
+            plain.push((idx, job));
+            continue;
+        }
+        let mut savepoint = tx.begin().await?;
+        let res = match apply_jitter(&mut *savepoint, &mut job).await {
+            Ok(()) if is_plain => {
+                savepoint.commit().await?;
+                plain.push((idx, job));
+                continue;
+            }
+            Ok(()) => create_deduped(&mut savepoint, job, tenant_id, instance_id).await,
+            Err(err) => Err(err),
+        };
+        results[idx] = Some(match res {
+            Ok(row) => {
+                savepoint.commit().await?;
+                Ok(row)
+            }
+            Err(err) if !atomic => {
+                savepoint.rollback().await?;
+                Err(err)
+            }
+            Err(err) => return Err(err),
+        });
+    }
+    let (indexes, plain): (Vec<usize>, Vec<JobCreate>) = plain.into_iter().unzip();
+    let mut savepoint = tx.begin().await?;
+    match create_many(&mut *savepoint, &plain, tenant_id, instance_id).await {
+        Ok(ids) => {
+            savepoint.commit().await?;
+            for (idx, id) in indexes.into_iter().zip(ids) {
+                results[idx] = Some(Ok(JobCreateRow {
+                    id,
+                    schedule_id: None,
+                    external_id: None,
+                }));
+            }
+        }
+        Err(err) if atomic => return Err(err),
+        // Find the failing items one by one
+        Err(_) => {
+            savepoint.rollback().await?;
+            for (idx, job) in indexes.into_iter().zip(plain) {
+                let mut savepoint = tx.begin().await?;
+                results[idx] = Some(
+                    match create_job(&mut *savepoint, job, tenant_id, instance_id).await {
+                        Ok(row) => {
+                            savepoint.commit().await?;
+                            Ok(row)
+                        }
+                        Err(err) => {
+                            savepoint.rollback().await?;
+                            Err(err)
+                        }
+                    },
+                );
+            }
+        }
+    }
+    tx.commit().await?;
+    Ok(results.into_iter().flatten().collect())
+}
+
+/// One-off jobs are offset from the external id when present, otherwise from
+/// their id, allocated here
+async fn apply_jitter(conn: impl PgExecutor<'_>, job: &mut JobCreate) -> Result<(), Error> {
+    let (None, Some(jitter)) = (&job.schedule, job.meta.jitter) else {
+        return Ok(());
+    };
+    i32::try_from(jitter).map_err(|_| Error::InvalidParams("jitter"))?;
+    let seed = match &job.external_id {
+        Some(external_id) => external_id.clone(),
+        None => {
+            const SQL: &str = "SELECT nextval(pg_get_serial_sequence('jobs', 'id'))";
+            let id = sqlx::query_scalar::<_, i64>(SQL).fetch_one(conn).await?;
+            job.id = Some(id);
+            id.to_string()
+        }
+    };
+    let at = job.at.unwrap_or_else(JobSchedule::now_secs);
+    job.at = Some(at + JobSchedule::jitter_offset(&seed, jitter));
+    Ok(())
+}
+
+async fn create_deduped(
+    conn: &mut PgConnection,
     job: JobCreate,
+    tenant_id: &str,
     instance_id: &str,
 ) -> Result<JobCreateRow, Error> {
-    let external_id = &job.external_id.clone();
-    let res = if job.schedule.is_some() {
-        create_with_schedule(pool, job, instance_id).await
-    } else if job.at.is_some() {
-        create_at(pool, job, instance_id).await
-    } else {
-        create_enqueue(pool, job, instance_id).await
+    let Some(external_id) = job.external_id.clone() else {
+        return create_job(conn, job, tenant_id, instance_id).await;
     };
-    // Check existing Job for idempotency
-    if let Err(Error::DbError(sqlx::Error::Database(ref err))) = res {
-        if err.code().unwrap_or_default() == "23505" {
-            if let Some(external_id) = external_id {
-                let existed_job = get_id_by_external_id(pool, external_id).await?;
-                if let Some(job) = existed_job {
-                    return Ok(job);
+    // Serialize creates with the same external id for idempotency
+    sqlx::query("SELECT pg_advisory_xact_lock(hashtext($1 || ':' || $2))")
+        .bind(tenant_id)
+        .bind(&external_id)
+        .execute(&mut *conn)
+        .await?;
+    if let Some(existing) = get_active_by_external_id(&mut *conn, tenant_id, &external_id).await? {
+        match job.id_conflict {
+            IdConflict::ReturnExisting => return Ok(existing),
+            IdConflict::Reject => return Err(Error::ExternalIdConflict(external_id)),
+            IdConflict::ReplaceIfPending => {
+                let cancelled =
+                    results::cancel(&mut *conn, tenant_id, existing.id, instance_id).await?;
+                if let Some(schedule_id) = replaced_schedule(cancelled, external_id)? {
+                    schedules::inactive(&mut *conn, tenant_id, &schedule_id, true).await?;
                 }
             }
         }
     }
-    res
+    create_job(conn, job, tenant_id, instance_id).await
+}
+
+/// Schedule of a job replaced by `replace_if_pending`, it ends with the replaced
+/// occurrence instead of running next to the new job
+fn replaced_schedule(
+    cancelled: CancelOutcome,
+    external_id: String,
+) -> Result<Option<String>, Error> {
+    match cancelled {
+        CancelOutcome::Cancelled { schedule_id, .. } => Ok(schedule_id),
+        _ => Err(Error::ExternalIdConflict(external_id)),
+    }
+}
+
+/// Multi-row insert of jobs without schedule and external id, ids are pre-allocated to keep the order
+async fn create_many(
+    conn: impl PgExecutor<'_>,
+    jobs: &[JobCreate],
+    tenant_id: &str,
+    instance_id: &str,
+) -> Result<Vec<i64>, Error> {
+    const SQL: &str = "
+    WITH input AS (
+        SELECT COALESCE(t.job_id, nextval(pg_get_serial_sequence('jobs', 'id'))) as id, t.*
+        FROM unnest($1::jsonb[], $2::jsonb[], $3::bytea[], $4::bigint[], $6::text[], $7::text[], $9::jsonb[], $10::bigint[]) WITH ORDINALITY AS t(meta, headers, body, at, body_ref, body_codec, envelope, job_id, ord)
+    ), a AS (
+        INSERT INTO jobs(id, meta, headers, body, body_ref, body_codec, envelope, tenant_id) SELECT id, meta, headers, body, body_ref, body_codec, envelope, $8 FROM input RETURNING id
+    ), hist AS (
+        INSERT INTO history(id, retry, instance_id, at, status, tenant_id) SELECT id, 0 as retry, $5 as instance_id, now() as at,
+            (CASE WHEN at IS NULL THEN 'enqueued' ELSE 'scheduled' END)::history_status as status, $8 as tenant_id
+        FROM input RETURNING id
+    ), s AS (
+        INSERT INTO scheduled SELECT id, at FROM input WHERE at IS NOT NULL RETURNING id
+    ), e AS (
+        INSERT INTO enqueued SELECT id FROM input WHERE at IS NULL RETURNING id
+    )
+    SELECT id FROM input ORDER BY ord";
+
+    if jobs.is_empty() {
+        return Ok(Vec::new());
+    }
+    let metas: Vec<Json<&JobMeta>> = jobs.iter().map(|job| Json(&job.meta)).collect();
+    let headers: Vec<Json<&Option<HashMap<String, String>>>> =
+        jobs.iter().map(|job| Json(&job.headers)).collect();
+    let bodies: Vec<Option<&[u8]>> = jobs
+        .iter()
+        .map(|job| (!job.body.is_empty()).then_some(job.body.as_ref()))
+        .collect();
+    let ats: Vec<Option<i64>> = jobs.iter().map(|job| job.at).collect();
+    let body_refs: Vec<Option<&str>> = jobs.iter().map(|job| job.body_ref.as_deref()).collect();
+    let body_codecs: Vec<Option<String>> = jobs
+        .iter()
+        .map(|job| job.body_codec.map(|c| c.to_string()))
+        .collect();
+    let envelopes: Vec<Option<Json<&Envelope>>> = jobs
+        .iter()
+        .map(|job| job.envelope.as_ref().map(Json))
+        .collect();
+    let job_ids: Vec<Option<i64>> = jobs.iter().map(|job| job.id).collect();
+    let ids = sqlx::query_scalar::<_, i64>(SQL)
+        .bind(metas)
+        .bind(headers)
+        .bind(bodies)
+        .bind(ats)
+        .bind(instance_id)
+        .bind(body_refs)
+        .bind(body_codecs)
+        .bind(tenant_id)
+        .bind(envelopes)
+        .bind(job_ids)
+        .fetch_all(conn)
+        .await?;
+    Ok(ids)
+}
+
+async fn create_job(
+    conn: impl PgExecutor<'_>,
+    job: JobCreate,
+    tenant_id: &str,
+    instance_id: &str,
+) -> Result<JobCreateRow, Error> {
+    if job.schedule.is_some() {
+        create_with_schedule(conn, job, tenant_id, instance_id).await
+    } else if job.at.is_some() {
+        create_at(conn, job, tenant_id, instance_id).await
+    } else {
+        create_enqueue(conn, job, tenant_id, instance_id).await
+    }
+}
+
+/// Dedup window end in Unix Time, `None` keeps the external id forever
+fn external_id_until(job: &JobCreate) -> Option<i64> {
+    job.external_id.as_ref()?;
+    job.external_id_ttl
+        .map(|ttl| JobSchedule::now_secs() + i64::from(ttl))
 }
 
 async fn create_enqueue(
-    pool: &Pool<Postgres>,
+    conn: impl PgExecutor<'_>,
     job: JobCreate,
+    tenant_id: &str,
     instance_id: &str,
 ) -> Result<JobCreateRow, Error> {
     const SQL: &str = "
     WITH a AS (
-        INSERT INTO jobs(meta, headers, body, external_id) VALUES ($1, $2, $3, $4) RETURNING id
+        INSERT INTO jobs(meta, headers, body, external_id, external_id_until, body_ref, body_codec, tenant_id, envelope) VALUES ($1, $2, $3, $4, $6, $7, $8, $9, $10) RETURNING id, tenant_id
     ), hist AS (
-        INSERT INTO history SELECT id, 0 as retry, $5 as instance_id, now() as at, 'enqueued'::history_status as status FROM a RETURNING id
+        INSERT INTO history(id, retry, instance_id, at, status, tenant_id) SELECT id, 0 as retry, $5 as instance_id, now() as at, 'enqueued'::history_status as status, tenant_id FROM a RETURNING id
     )
     INSERT INTO enqueued SELECT id FROM a RETURNING id";
 
@@ -54,7 +288,12 @@ async fn create_enqueue(
         .bind(body)
         .bind(&job.external_id)
         .bind(instance_id)
-        .fetch_one(pool)
+        .bind(external_id_until(&job))
+        .bind(&job.body_ref)
+        .bind(job.body_codec.map(|c| c.to_string()))
+        .bind(tenant_id)
+        .bind(job.envelope.as_ref().map(Json))
+        .fetch_one(conn)
         .await?;
     Ok(JobCreateRow {
         id: job_id,
@@ -64,15 +303,17 @@ async fn create_enqueue(
 }
 
 async fn create_at(
-    pool: &Pool<Postgres>,
+    conn: impl PgExecutor<'_>,
     job: JobCreate,
+    tenant_id: &str,
     instance_id: &str,
 ) -> Result<JobCreateRow, Error> {
     const SQL: &str = "
     WITH a AS (
-        INSERT INTO jobs(meta, headers, body, external_id) VALUES ($1, $2, $3, $4) RETURNING id
+        INSERT INTO jobs(id, meta, headers, body, external_id, external_id_until, body_ref, body_codec, tenant_id, envelope)
+        VALUES (COALESCE($12, nextval(pg_get_serial_sequence('jobs', 'id'))), $1, $2, $3, $4, $7, $8, $9, $10, $11) RETURNING id, tenant_id
     ), hist AS (
-        INSERT INTO history SELECT id, 0 as retry, $6 as instance_id, now() as at, 'scheduled'::history_status as status FROM a RETURNING id
+        INSERT INTO history(id, retry, instance_id, at, status, tenant_id) SELECT id, 0 as retry, $6 as instance_id, now() as at, 'scheduled'::history_status as status, tenant_id FROM a RETURNING id
     )
     INSERT INTO scheduled SELECT id, $5 as at FROM a RETURNING id
     ";
@@ -91,7 +332,13 @@ async fn create_at(
         .bind(&job.external_id)
         .bind(at)
         .bind(instance_id)
-        .fetch_one(pool)
+        .bind(external_id_until(&job))
+        .bind(&job.body_ref)
+        .bind(job.body_codec.map(|c| c.to_string()))
+        .bind(tenant_id)
+        .bind(job.envelope.as_ref().map(Json))
+        .bind(job.id)
+        .fetch_one(conn)
         .await?;
     Ok(JobCreateRow {
         id: job_id,
@@ -101,34 +348,43 @@ async fn create_at(
 }
 
 async fn create_with_schedule(
-    pool: &Pool<Postgres>,
+    conn: impl PgExecutor<'_>,
     job: JobCreate,
+    tenant_id: &str,
     instance_id: &str,
 ) -> Result<JobCreateRow, Error> {
     const SQL: &str = "
     WITH a AS (
-        INSERT INTO jobs(meta, headers, body, external_id, schedule_id) VALUES ($1, $2, $3, $4, $6) RETURNING id
+        INSERT INTO jobs(meta, headers, body, external_id, schedule_id, external_id_until, body_ref, body_codec, tenant_id, envelope) VALUES ($1, $2, $3, $4, $6, $12, $13, $14, $15, $16) RETURNING id, tenant_id
     ), b AS (
-        INSERT INTO schedules SELECT $6 as schedule_id, $7 as schedule, id as next_id, $5 as next_at FROM a RETURNING next_id
+        INSERT INTO schedules(schedule_id, schedule, next_id, next_at, until, jitter, anchor, tenant_id)
+        SELECT $6 as schedule_id, $7 as schedule, id as next_id, $5 as next_at, $9 as until, $10 as jitter, $11 as anchor, tenant_id FROM a RETURNING next_id
     ), hist AS (
-        INSERT INTO history SELECT id, 0 as retry, $8 as instance_id, now() as at, 'scheduled'::history_status as status FROM a RETURNING id
+        INSERT INTO history(id, retry, instance_id, at, status, tenant_id) SELECT id, 0 as retry, $8 as instance_id, now() as at, 'scheduled'::history_status as status, tenant_id FROM a RETURNING id
     )
     INSERT INTO scheduled SELECT id, $5 as at FROM a RETURNING id
     ";
 
-    let schedule = job.schedule.unwrap();
+    let schedule = job.schedule.clone().unwrap();
 
     let body: Option<&[u8]> = match job.body.is_empty() {
         true => None,
         false => Some(job.body.as_ref()),
     };
 
+    let schedule_id = ulid::Ulid::new().to_string();
+    let jitter = job.meta.jitter;
+    let stored_jitter = jitter
+        .map(i32::try_from)
+        .transpose()
+        .map_err(|_| Error::InvalidParams("jitter"))?;
+    let offset = schedule.anchor_offset(job.anchor)
+        + jitter.map_or(0, |j| JobSchedule::jitter_offset(&schedule_id, j));
     let after = job.at.unwrap_or_else(JobSchedule::now_secs);
-    let at = schedule.next(after, job.until);
+    let at = schedule.next_with_offset(after, job.until, offset);
     if at.is_none() {
         return Err(Error::InvalidParams("schedule"));
     }
-    let schedule_id = ulid::Ulid::new().to_string();
 
     let job_id = sqlx::query_scalar::<_, i64>(SQL)
         .bind(Json(&job.meta))
@@ -139,7 +395,15 @@ async fn create_with_schedule(
         .bind(&schedule_id)
         .bind(schedule.to_string())
         .bind(instance_id)
-        .fetch_one(pool)
+        .bind(job.until)
+        .bind(stored_jitter)
+        .bind(job.anchor)
+        .bind(external_id_until(&job))
+        .bind(&job.body_ref)
+        .bind(job.body_codec.map(|c| c.to_string()))
+        .bind(tenant_id)
+        .bind(job.envelope.as_ref().map(Json))
+        .fetch_one(conn)
         .await?;
     Ok(JobCreateRow {
         id: job_id,
@@ -156,18 +420,18 @@ pub async fn clone_schedule_at(
 ) -> Result<i64, Error> {
     const SQL: &str = "
     WITH a AS (
-        INSERT INTO jobs(meta, headers, body, schedule_id)
-        SELECT meta, headers, body, schedule_id
+        INSERT INTO jobs(meta, headers, body, schedule_id, body_ref, body_codec, envelope, tenant_id)
+        SELECT meta, headers, body, schedule_id, body_ref, body_codec, envelope, tenant_id
         FROM jobs
         WHERE id = $1
-        RETURNING id, schedule_id
+        RETURNING id, schedule_id, tenant_id
     ), b AS (
         UPDATE schedules
         SET next_id = a.id, next_at = $2
         FROM a
         WHERE schedules.schedule_id = a.schedule_id
     ), hist AS (
-        INSERT INTO history SELECT id, 0 as retry, $3 as instance_id, now() as at, 'scheduled'::history_status as status FROM a RETURNING id
+        INSERT INTO history(id, retry, instance_id, at, status, tenant_id) SELECT id, 0 as retry, $3 as instance_id, now() as at, 'scheduled'::history_status as status, tenant_id FROM a RETURNING id
     )
     INSERT INTO scheduled SELECT id, $2 as at FROM a RETURNING id
     ";
@@ -181,7 +445,52 @@ pub async fn clone_schedule_at(
     Ok(job_id)
 }
 
-pub async fn get_by_id(pool: &Pool<Postgres>, job_id: i64) -> Result<Option<JobRow>, Error> {
+/// Enqueues a copy of a job of the tenant, without its schedule and external id
+pub async fn replay(
+    pool: &Pool<Postgres>,
+    tenant_id: &str,
+    job_id: i64,
+    instance_id: &str,
+) -> Result<Option<JobCreateRow>, Error> {
+    const SQL: &str = "
+    WITH a AS (
+        INSERT INTO jobs(meta, headers, body, body_ref, body_codec, envelope, tenant_id)
+        SELECT meta, headers, body, body_ref, body_codec, envelope, tenant_id
+        FROM jobs
+        WHERE id = $1 AND tenant_id = $2
+        RETURNING id, schedule_id, external_id, tenant_id
+    ), hist AS (
+        INSERT INTO history(id, retry, instance_id, at, status, tenant_id) SELECT id, 0 as retry, $3 as instance_id, now() as at, 'enqueued'::history_status as status, tenant_id FROM a RETURNING id
+    ), e AS (
+        INSERT INTO enqueued SELECT id FROM a RETURNING id
+    )
+    SELECT id, schedule_id, external_id FROM a
+    ";
+    let job = sqlx::query_as::<_, JobCreateRow>(SQL)
+        .bind(job_id)
+        .bind(tenant_id)
+        .bind(instance_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(job)
+}
+
+pub async fn get_by_id(
+    pool: &Pool<Postgres>,
+    tenant_id: &str,
+    job_id: i64,
+) -> Result<Option<JobRow>, Error> {
+    const SQL: &str = "SELECT * FROM jobs WHERE id = $1 AND tenant_id = $2";
+    let job = sqlx::query_as::<_, JobRow>(SQL)
+        .bind(job_id)
+        .bind(tenant_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(job)
+}
+
+/// Job of any tenant, for workers running an assigned job
+pub async fn get_to_run(pool: &Pool<Postgres>, job_id: i64) -> Result<Option<JobRow>, Error> {
     const SQL: &str = "SELECT * FROM jobs WHERE id = $1";
     let job = sqlx::query_as::<_, JobRow>(SQL)
         .bind(job_id)
@@ -190,21 +499,189 @@ pub async fn get_by_id(pool: &Pool<Postgres>, job_id: i64) -> Result<Option<JobR
     Ok(job)
 }
 
+pub async fn search(
+    pool: &Pool<Postgres>,
+    tenant_id: &str,
+    search: &JobSearch,
+    cursor: Option<i64>,
+    limit: i32,
+) -> Result<Vec<JobListRow>, Error> {
+    if search.state.is_some() {
+        return search_by_state(pool, tenant_id, search, cursor, limit).await;
+    }
+    const SQL: &str = "
+    SELECT * FROM (
+        SELECT j.id, j.meta, j.headers, j.body, j.schedule_id, j.external_id, j.body_ref, j.body_codec, j.tenant_id, j.envelope, j.created_at,
+            CASE
+                WHEN p.id IS NOT NULL THEN p.status::text
+                WHEN e.id IS NOT NULL AND e.lock_at IS NULL THEN 'enqueued'
+                WHEN e.id IS NOT NULL THEN 'assigned'
+                WHEN s.id IS NOT NULL THEN 'scheduled'
+            END as state,
+            COALESCE(p.retry, e.retry, s.retry, 0) as retry,
+            p.at as processed_at
+        FROM jobs j
+        LEFT JOIN scheduled s ON s.id = j.id
+        LEFT JOIN enqueued e ON e.id = j.id
+        LEFT JOIN processed p ON p.id = j.id
+        WHERE j.tenant_id = $13 AND $2::text IS NULL
+        AND ($1::bigint IS NULL OR j.id < $1)
+        AND ($3::text IS NULL OR j.host = lower($3))
+        AND ($4::text IS NULL OR j.meta->>'method' = $4)
+        AND ($5::text IS NULL OR j.schedule_id = $5)
+        AND ($6::text IS NULL OR j.external_id LIKE $6)
+        AND ($7::timestamptz IS NULL OR j.created_at >= $7)
+        AND ($8::timestamptz IS NULL OR j.created_at < $8)
+        AND ($9::timestamptz IS NULL OR p.at >= $9)
+        AND ($10::timestamptz IS NULL OR p.at < $10)
+    ) a
+    WHERE ($11::int IS NULL OR a.retry >= $11)
+    ORDER BY a.id DESC
+    LIMIT $12";
+    search_query(pool, SQL, tenant_id, search, cursor, limit).await
+}
+
+/// Jobs of a state are read from the table of the state newest first, each
+/// branch stops after `limit` matching jobs
+async fn search_by_state(
+    pool: &Pool<Postgres>,
+    tenant_id: &str,
+    search: &JobSearch,
+    cursor: Option<i64>,
+    limit: i32,
+) -> Result<Vec<JobListRow>, Error> {
+    const SQL: &str = "
+    WITH c AS (
+        (SELECT p.id, p.retry, p.status::text as state, p.at as processed_at
+        FROM processed p INNER JOIN jobs j ON j.id = p.id
+        WHERE p.tenant_id = $13 AND j.tenant_id = $13
+        AND p.status = (CASE WHEN $2 IN ('completed', 'failed', 'cancelled') THEN $2 END)::processed_status
+        AND ($9::timestamptz IS NULL OR p.at >= $9)
+        AND ($10::timestamptz IS NULL OR p.at < $10)
+        AND ($11::int IS NULL OR p.retry >= $11)
+        AND ($1::bigint IS NULL OR p.id < $1)
+        AND ($3::text IS NULL OR j.host = lower($3))
+        AND ($4::text IS NULL OR j.meta->>'method' = $4)
+        AND ($5::text IS NULL OR j.schedule_id = $5)
+        AND ($6::text IS NULL OR j.external_id LIKE $6)
+        AND ($7::timestamptz IS NULL OR j.created_at >= $7)
+        AND ($8::timestamptz IS NULL OR j.created_at < $8)
+        ORDER BY p.id DESC
+        LIMIT $12)
+        UNION ALL
+        (SELECT e.id, e.retry, $2 as state, NULL as processed_at
+        FROM enqueued e INNER JOIN jobs j ON j.id = e.id
+        WHERE $2 IN ('enqueued', 'assigned') AND (e.lock_at IS NULL) = ($2 = 'enqueued')
+        AND $9::timestamptz IS NULL AND $10::timestamptz IS NULL
+        AND j.tenant_id = $13
+        AND ($11::int IS NULL OR e.retry >= $11)
+        AND ($1::bigint IS NULL OR e.id < $1)
+        AND ($3::text IS NULL OR j.host = lower($3))
+        AND ($4::text IS NULL OR j.meta->>'method' = $4)
+        AND ($5::text IS NULL OR j.schedule_id = $5)
+        AND ($6::text IS NULL OR j.external_id LIKE $6)
+        AND ($7::timestamptz IS NULL OR j.created_at >= $7)
+        AND ($8::timestamptz IS NULL OR j.created_at < $8)
+        ORDER BY e.id DESC
+        LIMIT $12)
+        UNION ALL
+        (SELECT s.id, s.retry, $2 as state, NULL as processed_at
+        FROM scheduled s INNER JOIN jobs j ON j.id = s.id
+        WHERE $2 = 'scheduled' AND $9::timestamptz IS NULL AND $10::timestamptz IS NULL
+        AND j.tenant_id = $13
+        AND ($11::int IS NULL OR s.retry >= $11)
+        AND ($1::bigint IS NULL OR s.id < $1)
+        AND ($3::text IS NULL OR j.host = lower($3))
+        AND ($4::text IS NULL OR j.meta->>'method' = $4)
+        AND ($5::text IS NULL OR j.schedule_id = $5)
+        AND ($6::text IS NULL OR j.external_id LIKE $6)
+        AND ($7::timestamptz IS NULL OR j.created_at >= $7)
+        AND ($8::timestamptz IS NULL OR j.created_at < $8)
+        ORDER BY s.id DESC
+        LIMIT $12)
+    )
+    SELECT j.id, j.meta, j.headers, j.body, j.schedule_id, j.external_id, j.body_ref, j.body_codec, j.tenant_id, j.envelope, j.created_at,
+        c.state, c.retry, c.processed_at
+    FROM c INNER JOIN jobs j ON j.id = c.id
+    ORDER BY j.id DESC
+    LIMIT $12";
+    search_query(pool, SQL, tenant_id, search, cursor, limit).await
+}
+
+async fn search_query(
+    pool: &Pool<Postgres>,
+    sql: &'static str,
+    tenant_id: &str,
+    search: &JobSearch,
+    cursor: Option<i64>,
+    limit: i32,
+) -> Result<Vec<JobListRow>, Error> {
+    let external_id_like = search.external_id_prefix.as_ref().map(|prefix| {
+        let escaped = prefix
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        format!("{}%", escaped)
+    });
+    let rows = sqlx::query_as::<_, JobListRow>(sql)
+        .bind(cursor)
+        .bind(&search.state)
+        .bind(&search.host)
+        .bind(search.method.as_ref().map(|m| m.to_uppercase()))
+        .bind(&search.schedule_id)
+        .bind(external_id_like)
+        .bind(search.created_from)
+        .bind(search.created_to)
+        .bind(search.processed_from)
+        .bind(search.processed_to)
+        .bind(search.min_retry)
+        .bind(limit)
+        .bind(tenant_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(rows)
+}
+
+/// Newest job with the external id
 pub async fn get_id_by_external_id(
     pool: &Pool<Postgres>,
+    tenant_id: &str,
     external_id: &str,
 ) -> Result<Option<JobCreateRow>, Error> {
-    const SQL: &str = "SELECT id, schedule_id, external_id FROM jobs WHERE external_id = $1";
+    const SQL: &str = "SELECT id, schedule_id, external_id FROM jobs WHERE tenant_id = $1 AND external_id = $2 ORDER BY id DESC LIMIT 1";
     let job = sqlx::query_as::<_, JobCreateRow>(SQL)
+        .bind(tenant_id)
         .bind(external_id)
         .fetch_optional(pool)
         .await?;
     Ok(job)
 }
 
-pub async fn delete(pool: &Pool<Postgres>, job_id: i64) -> Result<u64, Error> {
-    const SQL: &str = "DELETE FROM jobs WHERE id = $1";
-    let res = sqlx::query(SQL).bind(job_id).execute(pool).await?;
+/// Newest job with the external id whose dedup window has not passed
+async fn get_active_by_external_id(
+    conn: impl PgExecutor<'_>,
+    tenant_id: &str,
+    external_id: &str,
+) -> Result<Option<JobCreateRow>, Error> {
+    const SQL: &str = "
+    SELECT id, schedule_id, external_id FROM jobs
+    WHERE tenant_id = $1 AND external_id = $2 AND (external_id_until IS NULL OR external_id_until > extract(epoch from now())::bigint)
+    ORDER BY id DESC LIMIT 1";
+    let job = sqlx::query_as::<_, JobCreateRow>(SQL)
+        .bind(tenant_id)
+        .bind(external_id)
+        .fetch_optional(conn)
+        .await?;
+    Ok(job)
+}
+
+pub async fn delete(pool: &Pool<Postgres>, tenant_id: &str, job_id: i64) -> Result<u64, Error> {
+    const SQL: &str = "DELETE FROM jobs WHERE id = $1 AND tenant_id = $2";
+    let res = sqlx::query(SQL)
+        .bind(job_id)
+        .bind(tenant_id)
+        .execute(pool)
+        .await?;
     Ok(res.rows_affected())
 }
 
@@ -215,7 +692,8 @@ pub async fn enqueue_scheduled(pool: &Pool<Postgres>, instance_id: &str) -> Resu
     ), b AS (
         INSERT INTO enqueued SELECT id, retry FROM a RETURNING id
     ), hist AS (
-        INSERT INTO history SELECT id, retry, $2 as instance_id, now() as at, 'enqueued'::history_status as status FROM a RETURNING id
+        INSERT INTO history(id, retry, instance_id, at, status, tenant_id)
+        SELECT a.id, a.retry, $2 as instance_id, now() as at, 'enqueued'::history_status as status, j.tenant_id FROM a INNER JOIN jobs j ON j.id = a.id RETURNING id
     )
     DELETE FROM scheduled WHERE id = ANY(SELECT id FROM b)";
     let res = sqlx::query(SQL)
@@ -231,7 +709,8 @@ pub async fn unlock(pool: &Pool<Postgres>, job_id: i64, instance_id: &str) -> Re
     WITH a AS (
         UPDATE enqueued SET instance_id = null, lock_at = null, retry = retry + 1 WHERE id = $1 RETURNING id, retry
     )
-    INSERT INTO history SELECT id, retry, $2 as instance_id, now() as at, 'retried'::history_status as status FROM a RETURNING id";
+    INSERT INTO history(id, retry, instance_id, at, status, tenant_id)
+    SELECT a.id, a.retry, $2 as instance_id, now() as at, 'retried'::history_status as status, j.tenant_id FROM a INNER JOIN jobs j ON j.id = a.id RETURNING id";
     let res = sqlx::query(SQL)
         .bind(job_id)
         .bind(instance_id)
@@ -245,7 +724,8 @@ pub async fn retry(pool: &Pool<Postgres>, job_id: i64, at: i64) -> Result<u64, E
     WITH a AS (
         DELETE FROM enqueued WHERE id = $1 RETURNING id, retry, instance_id
     ), hist AS (
-        INSERT INTO history SELECT id, (retry + 1) as retry, instance_id, now() as at, 'retried'::history_status as status FROM a RETURNING id
+        INSERT INTO history(id, retry, instance_id, at, status, tenant_id)
+        SELECT a.id, (a.retry + 1) as retry, a.instance_id, now() as at, 'retried'::history_status as status, j.tenant_id FROM a INNER JOIN jobs j ON j.id = a.id RETURNING id
     )
     INSERT INTO scheduled SELECT id, $2 as at, (retry + 1) as retry FROM a RETURNING id";
     let res = sqlx::query(SQL).bind(job_id).bind(at).execute(pool).await?;
@@ -261,7 +741,8 @@ pub fn fetch_enqueued<'a>(
     const SQL: &str = "WITH a AS (
         SELECT id, retry FROM enqueued WHERE lock_at IS NULL ORDER BY retry, id LIMIT $1 FOR UPDATE SKIP LOCKED
     ), hist AS (
-        INSERT INTO history SELECT id, retry, $2 as instance_id, now() as at, 'assigned'::history_status as status FROM a RETURNING id
+        INSERT INTO history(id, retry, instance_id, at, status, tenant_id)
+        SELECT a.id, a.retry, $2 as instance_id, now() as at, 'assigned'::history_status as status, j.tenant_id FROM a INNER JOIN jobs j ON j.id = a.id RETURNING id
     )
     UPDATE enqueued SET instance_id = $2, lock_at = now() WHERE id = ANY(SELECT id FROM a) RETURNING id, retry";
     sqlx::query_as::<_, JobEntry>(SQL)
@@ -278,7 +759,8 @@ pub async fn fetch_optional(
     const SQL: &str = "WITH a AS (
         SELECT id, retry FROM enqueued WHERE lock_at IS NULL ORDER BY retry, id LIMIT $1 FOR UPDATE SKIP LOCKED
     ), hist AS (
-        INSERT INTO history SELECT id, retry, $2 as instance_id, now() as at, 'assigned'::history_status as status FROM a RETURNING id
+        INSERT INTO history(id, retry, instance_id, at, status, tenant_id)
+        SELECT a.id, a.retry, $2 as instance_id, now() as at, 'assigned'::history_status as status, j.tenant_id FROM a INNER JOIN jobs j ON j.id = a.id RETURNING id
     )
     UPDATE enqueued SET instance_id = $2, lock_at = now() WHERE id = ANY(SELECT id FROM a) RETURNING id, retry";
     sqlx::query_as::<_, JobEntry>(SQL)
@@ -300,7 +782,8 @@ pub fn fetch_job_with_retry<'a>(
     ), b AS (
         UPDATE enqueued SET instance_id = $2, lock_at = now() WHERE id = ANY(SELECT id FROM a) RETURNING id, retry
     ), hist AS (
-        INSERT INTO history SELECT id, retry, $2 as instance_id, now() as at, 'assigned'::history_status as status FROM a RETURNING id
+        INSERT INTO history(id, retry, instance_id, at, status, tenant_id)
+        SELECT a.id, a.retry, $2 as instance_id, now() as at, 'assigned'::history_status as status, j.tenant_id FROM a INNER JOIN jobs j ON j.id = a.id RETURNING id
     )
     SELECT j.*, a.retry FROM a INNER JOIN jobs as j ON a.id = j.id
     ";
@@ -309,3 +792,29 @@ pub fn fetch_job_with_retry<'a>(
         .bind(instance_id)
         .fetch(pool)
 }
+
+#[tokio::test]
+async fn replaced_schedule_ends() -> anyhow::Result<()> {
+    // arrange
+    let scheduled = CancelOutcome::Cancelled {
+        schedule_id: Some("nightly".to_string()),
+        at: Some(1_700_000_000),
+    };
+    let enqueued = CancelOutcome::Cancelled {
+        schedule_id: None,
+        at: None,
+    };
+
+    // act & assert
+    assert_eq!(
+        Some("nightly".to_string()),
+        replaced_schedule(scheduled, "order-1".into())?
+    );
+    assert_eq!(None, replaced_schedule(enqueued, "order-1".into())?);
+    assert!(matches!(
+        replaced_schedule(CancelOutcome::Conflict, "order-1".into()),
+        Err(Error::ExternalIdConflict(id)) if id == "order-1"
+    ));
+    assert!(replaced_schedule(CancelOutcome::NotFound, "order-1".into()).is_err());
+    Ok(())
+}
@@ -1,2 +1,4 @@
+pub mod blobs;
+pub mod encryption;
 pub mod instances;
 pub mod jobqueue;
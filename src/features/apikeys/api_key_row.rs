@@ -0,0 +1,31 @@
+use serde::{Deserialize, Serialize};
+
+/// `key_hash` is left out, a key is only shown once on create
+#[derive(Debug, Clone, sqlx::FromRow, Serialize)]
+pub struct ApiKeyRow {
+    pub key_id: String,
+    pub name: String,
+    pub tenant_id: String,
+    /// First characters of the key, to recognize it in listings
+    pub prefix: String,
+    pub scopes: Vec<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub last_used_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub revoked_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ApiKeyCreate {
+    pub name: String,
+    /// Tenant of the caller when not set
+    pub tenant_id: Option<String>,
+    pub scopes: Vec<String>,
+}
+
+/// Returned once on create, the only time the key is exposed
+#[derive(Debug, Clone, Serialize)]
+pub struct ApiKeyCreated {
+    #[serde(flatten)]
+    pub api_key: ApiKeyRow,
+    pub key: String,
+}
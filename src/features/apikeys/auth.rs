@@ -0,0 +1,125 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use hyper::{HeaderMap, header};
+use problemdetails::Problem;
+use sha2::{Digest, Sha256};
+use tracing::{info, warn};
+
+use crate::models::{AppState, Error};
+
+use super::{ApiKeyCreate, ApiKeyRow, Scope, db};
+
+/// Environment variable of an admin key created at startup
+pub const ADMIN_KEY_ENV: &str = "IRISQO_ADMIN_KEY";
+const KEY_PREFIX: &str = "iq_";
+/// Characters of a key kept in `api_keys.prefix`
+const PREFIX_LEN: usize = 11;
+
+pub fn new_key() -> String {
+    format!("{}{}", KEY_PREFIX, uuid::Uuid::new_v4().simple())
+}
+
+/// Keys are random, a plain sha256 is enough to not store them
+pub fn hash_key(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+pub fn key_prefix(key: &str) -> &str {
+    &key[..key.len().min(PREFIX_LEN)]
+}
+
+/// Checks `Authorization: Bearer <key>` against the scope of the route,
+/// the key is added to the request extensions
+pub async fn auth(
+    State(state): State<Arc<AppState>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    if !state.auth {
+        return next.run(request).await;
+    }
+    let Some(required) = Scope::required(request.method(), request.uri().path()) else {
+        return next.run(request).await;
+    };
+    match authorize(&state, request.headers(), required).await {
+        Ok(api_key) => {
+            request.extensions_mut().insert(api_key);
+            next.run(request).await
+        }
+        Err(Error::Unauthorized) => {
+            let mut response = Problem::from(Error::Unauthorized).into_response();
+            response
+                .headers_mut()
+                .insert(header::WWW_AUTHENTICATE, "Bearer".parse().unwrap());
+            response
+        }
+        Err(err) => Problem::from(err).into_response(),
+    }
+}
+
+async fn authorize(
+    state: &AppState,
+    headers: &HeaderMap,
+    required: Scope,
+) -> Result<ApiKeyRow, Error> {
+    let key = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .ok_or(Error::Unauthorized)?;
+    let api_key = db::get_active_by_hash(&state.pool, &hash_key(key.trim()))
+        .await?
+        .ok_or(Error::Unauthorized)?;
+    let allowed = api_key
+        .scopes
+        .iter()
+        .filter_map(|s| s.parse::<Scope>().ok())
+        .any(|s| s.allows(required));
+    match allowed {
+        true => Ok(api_key),
+        false => Err(Error::Forbidden(required.as_str())),
+    }
+}
+
+/// Creates the admin key of `IRISQO_ADMIN_KEY` in the default tenant, warns when no key can pass
+pub async fn bootstrap(state: &AppState) -> Result<(), Error> {
+    if !state.auth {
+        warn!("authentication disabled, every route is public");
+        return Ok(());
+    }
+    if let Ok(key) = std::env::var(ADMIN_KEY_ENV) {
+        let api_key = ApiKeyCreate {
+            name: "bootstrap".to_string(),
+            tenant_id: None,
+            scopes: vec![Scope::Admin.to_string()],
+        };
+        let created =
+            db::create_if_missing(&state.pool, api_key, key_prefix(&key), &hash_key(&key)).await?;
+        if created > 0 {
+            info!("admin key created from {}", ADMIN_KEY_ENV);
+        }
+    }
+    if db::count_active(&state.pool).await? == 0 {
+        warn!("no active API key, set {} to create one", ADMIN_KEY_ENV);
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn new_key_hash() -> anyhow::Result<()> {
+    // act
+    let key = new_key();
+
+    // assert
+    assert!(key.starts_with(KEY_PREFIX));
+    assert_eq!(64, hash_key(&key).len());
+    assert_eq!(hash_key(&key), hash_key(&key));
+    assert_ne!(hash_key(&key), hash_key(&new_key()));
+    assert_eq!(PREFIX_LEN, key_prefix(&key).len());
+    Ok(())
+}
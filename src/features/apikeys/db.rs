@@ -0,0 +1,134 @@
+use crate::models::Error;
+
+use sqlx::{Pool, Postgres};
+
+use super::{ApiKeyCreate, ApiKeyRow};
+
+pub async fn create(
+    pool: &Pool<Postgres>,
+    api_key: ApiKeyCreate,
+    tenant_id: &str,
+    prefix: &str,
+    key_hash: &str,
+) -> Result<ApiKeyRow, Error> {
+    const SQL: &str = "
+    INSERT INTO api_keys(key_id, name, prefix, key_hash, scopes, tenant_id) VALUES ($1, $2, $3, $4, $5, $6) RETURNING *
+    ";
+    let key_id = ulid::Ulid::new().to_string();
+    let row = sqlx::query_as::<_, ApiKeyRow>(SQL)
+        .bind(key_id)
+        .bind(api_key.name)
+        .bind(prefix)
+        .bind(key_hash)
+        .bind(api_key.scopes)
+        .bind(tenant_id)
+        .fetch_one(pool)
+        .await?;
+    Ok(row)
+}
+
+/// Creates the key unless a key with the same hash exists, revoked or not
+pub async fn create_if_missing(
+    pool: &Pool<Postgres>,
+    api_key: ApiKeyCreate,
+    prefix: &str,
+    key_hash: &str,
+) -> Result<u64, Error> {
+    const SQL: &str = "
+    INSERT INTO api_keys(key_id, name, prefix, key_hash, scopes) VALUES ($1, $2, $3, $4, $5)
+    ON CONFLICT (key_hash) DO NOTHING
+    ";
+    let key_id = ulid::Ulid::new().to_string();
+    let res = sqlx::query(SQL)
+        .bind(key_id)
+        .bind(api_key.name)
+        .bind(prefix)
+        .bind(key_hash)
+        .bind(api_key.scopes)
+        .execute(pool)
+        .await?;
+    Ok(res.rows_affected())
+}
+
+/// Active key by hash, `last_used_at` is refreshed at most once a minute
+pub async fn get_active_by_hash(
+    pool: &Pool<Postgres>,
+    key_hash: &str,
+) -> Result<Option<ApiKeyRow>, Error> {
+    const SQL: &str = "
+    WITH k AS (
+        SELECT * FROM api_keys WHERE key_hash = $1 AND revoked_at IS NULL
+    ),
+    u AS (
+        UPDATE api_keys SET last_used_at = NOW()
+        WHERE key_id IN (
+            SELECT key_id FROM k
+            WHERE last_used_at IS NULL OR last_used_at < NOW() - interval '1 minute'
+        )
+    )
+    SELECT * FROM k
+    ";
+    let row = sqlx::query_as::<_, ApiKeyRow>(SQL)
+        .bind(key_hash)
+        .fetch_optional(pool)
+        .await?;
+    Ok(row)
+}
+
+/// `tenant_id` of `None` matches keys of every tenant
+pub async fn get_by_id(
+    pool: &Pool<Postgres>,
+    tenant_id: Option<&str>,
+    key_id: &str,
+) -> Result<Option<ApiKeyRow>, Error> {
+    const SQL: &str =
+        "SELECT * FROM api_keys WHERE key_id = $1 AND ($2::text IS NULL OR tenant_id = $2)";
+    let row = sqlx::query_as::<_, ApiKeyRow>(SQL)
+        .bind(key_id)
+        .bind(tenant_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(row)
+}
+
+pub async fn get_all(
+    pool: &Pool<Postgres>,
+    tenant_id: Option<&str>,
+    limit: i32,
+    offset: i32,
+) -> Result<Vec<ApiKeyRow>, Error> {
+    const SQL: &str = "
+    SELECT * FROM api_keys WHERE ($3::text IS NULL OR tenant_id = $3) ORDER BY key_id LIMIT $1 OFFSET $2;
+    ";
+    let res = sqlx::query_as::<_, ApiKeyRow>(SQL)
+        .bind(limit)
+        .bind(offset)
+        .bind(tenant_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(res)
+}
+
+pub async fn count_active(pool: &Pool<Postgres>) -> Result<i64, Error> {
+    const SQL: &str = "SELECT count(*) FROM api_keys WHERE revoked_at IS NULL";
+    let count: i64 = sqlx::query_scalar(SQL).fetch_one(pool).await?;
+    Ok(count)
+}
+
+/// Revoked keys are kept for auditing
+pub async fn revoke(
+    pool: &Pool<Postgres>,
+    tenant_id: Option<&str>,
+    key_id: &str,
+) -> Result<u64, Error> {
+    const SQL: &str = "
+    UPDATE api_keys SET revoked_at = NOW()
+    WHERE key_id = $1 AND revoked_at IS NULL AND ($2::text IS NULL OR tenant_id = $2)
+    ";
+    let res = sqlx::query(SQL)
+        .bind(key_id)
+        .bind(tenant_id)
+        .execute(pool)
+        .await?;
+    Ok(res.rows_affected())
+}
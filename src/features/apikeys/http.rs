@@ -0,0 +1,119 @@
+use crate::{
+    features::{Paging, PagingResult},
+    models::{AppState, Error},
+};
+use axum::{
+    Json, Router,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use problemdetails::Problem;
+use std::sync::Arc;
+
+use super::{ApiKeyCreate, ApiKeyCreated, DEFAULT_TENANT, Scope, Tenant, auth, is_valid_tenant_id};
+
+pub fn routes(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/keys/{id}", get(get_by_id).delete(revoke))
+        .route("/keys", get(get_all).post(create))
+        .with_state(state)
+}
+
+/// Admins of the default tenant manage the keys of every tenant
+fn managed_tenant(tenant_id: &str) -> Option<&str> {
+    (tenant_id != DEFAULT_TENANT).then_some(tenant_id)
+}
+
+async fn create(
+    State(state): State<Arc<AppState>>,
+    caller: Tenant,
+    Json(api_key): Json<ApiKeyCreate>,
+) -> Result<impl IntoResponse, Problem> {
+    if api_key.name.trim().is_empty() {
+        return Err(Error::InvalidParams("name").into());
+    }
+    if api_key.scopes.is_empty() {
+        return Err(Error::InvalidParams("scopes").into());
+    }
+    for scope in &api_key.scopes {
+        scope.parse::<Scope>()?;
+    }
+    let tenant_id = api_key
+        .tenant_id
+        .clone()
+        .unwrap_or_else(|| caller.0.clone());
+    if !is_valid_tenant_id(&tenant_id) || !caller.manages(&tenant_id) {
+        return Err(Error::InvalidParams("tenant_id").into());
+    }
+    let key = auth::new_key();
+    let row = super::db::create(
+        &state.pool,
+        api_key,
+        &tenant_id,
+        auth::key_prefix(&key),
+        &auth::hash_key(&key),
+    )
+    .await?;
+    Ok((
+        StatusCode::CREATED,
+        Json(ApiKeyCreated { api_key: row, key }),
+    ))
+}
+
+async fn get_all(
+    State(state): State<Arc<AppState>>,
+    Tenant(tenant_id): Tenant,
+    Query(pagination): Query<Paging>,
+) -> Result<impl IntoResponse, Problem> {
+    let result = super::db::get_all(
+        &state.pool,
+        managed_tenant(&tenant_id),
+        pagination.limit.unwrap_or(10),
+        pagination.offset.unwrap_or(0),
+    )
+    .await?;
+    Ok(Json(PagingResult {
+        limit: pagination.limit.unwrap_or(10),
+        offset: pagination.offset.unwrap_or(0),
+        data: result,
+    }))
+}
+
+async fn get_by_id(
+    State(state): State<Arc<AppState>>,
+    Tenant(tenant_id): Tenant,
+    Path(key_id): Path<String>,
+) -> Result<Response, Problem> {
+    let row = super::db::get_by_id(&state.pool, managed_tenant(&tenant_id), &key_id).await?;
+    match row {
+        None => Ok(StatusCode::NO_CONTENT.into_response()),
+        Some(o) => Ok(Json(o).into_response()),
+    }
+}
+
+async fn revoke(
+    State(state): State<Arc<AppState>>,
+    Tenant(tenant_id): Tenant,
+    Path(key_id): Path<String>,
+) -> Result<Response, Problem> {
+    let rows = super::db::revoke(&state.pool, managed_tenant(&tenant_id), &key_id).await?;
+    match rows {
+        0 => Ok(StatusCode::NOT_FOUND.into_response()),
+        _ => Ok(StatusCode::NO_CONTENT.into_response()),
+    }
+}
+
+#[tokio::test]
+async fn tenant_id_validation() -> anyhow::Result<()> {
+    // act & assert
+    assert!(is_valid_tenant_id("team-payments_2"));
+    assert!(!is_valid_tenant_id(""));
+    assert!(!is_valid_tenant_id("team a"));
+    assert_eq!(None, managed_tenant(DEFAULT_TENANT));
+    assert_eq!(Some("team-a"), managed_tenant("team-a"));
+    assert!(Tenant(DEFAULT_TENANT.to_string()).manages("team-a"));
+    assert!(!Tenant("team-b".to_string()).manages("team-a"));
+    Ok(())
+}
@@ -0,0 +1,14 @@
+pub use api_key_row::{ApiKeyCreate, ApiKeyCreated, ApiKeyRow};
+pub use auth::{auth, bootstrap};
+pub use http::routes;
+pub use scope::Scope;
+pub use tenant::{DEFAULT_TENANT, Tenant, is_valid_tenant_id};
+pub use unredacted::Unredacted;
+
+mod api_key_row;
+mod auth;
+mod db;
+mod http;
+mod scope;
+mod tenant;
+mod unredacted;
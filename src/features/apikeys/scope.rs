@@ -0,0 +1,109 @@
+use std::{fmt::Display, str::FromStr};
+
+use hyper::Method;
+
+use crate::models::Error;
+
+/// Permission of an API key, `admin` allows everything
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    Enqueue,
+    Read,
+    Admin,
+}
+
+/// Routes reachable without a key
+const PUBLIC_PATHS: [&str; 3] = ["/", "/live", "/ready"];
+
+impl Scope {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Scope::Enqueue => "enqueue",
+            Scope::Read => "read",
+            Scope::Admin => "admin",
+        }
+    }
+
+    pub fn allows(self, required: Scope) -> bool {
+        self == Scope::Admin || self == required
+    }
+
+    /// Scope a request needs, `None` for public routes
+    pub fn required(method: &Method, path: &str) -> Option<Scope> {
+        if PUBLIC_PATHS.contains(&path) {
+            return None;
+        }
+        if path.starts_with("/to/") {
+            return Some(Scope::Enqueue);
+        }
+        if path == "/api/v1/keys" || path.starts_with("/api/v1/keys/") {
+            return Some(Scope::Admin);
+        }
+        if method == Method::GET || method == Method::HEAD {
+            return Some(Scope::Read);
+        }
+        let enqueue = match *method {
+            Method::POST => path == "/api/v1/jobs" || path == "/api/v1/jobs/batch",
+            Method::PUT => path.starts_with("/api/v1/jobs/") && path.ends_with("/cancel"),
+            _ => false,
+        };
+        match enqueue {
+            true => Some(Scope::Enqueue),
+            false => Some(Scope::Admin),
+        }
+    }
+}
+
+impl Display for Scope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for Scope {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "enqueue" => Ok(Scope::Enqueue),
+            "read" => Ok(Scope::Read),
+            "admin" => Ok(Scope::Admin),
+            _ => Err(Error::InvalidParams("scopes")),
+        }
+    }
+}
+
+#[tokio::test]
+async fn scope_required() -> anyhow::Result<()> {
+    // act & assert
+    assert_eq!(None, Scope::required(&Method::GET, "/live"));
+    assert_eq!(Some(Scope::Read), Scope::required(&Method::GET, "/echo"));
+    assert_eq!(Some(Scope::Admin), Scope::required(&Method::POST, "/error"));
+    assert_eq!(
+        Some(Scope::Enqueue),
+        Scope::required(&Method::DELETE, "/to/https://example.com")
+    );
+    assert_eq!(
+        Some(Scope::Enqueue),
+        Scope::required(&Method::POST, "/api/v1/jobs/batch")
+    );
+    assert_eq!(
+        Some(Scope::Enqueue),
+        Scope::required(&Method::PUT, "/api/v1/jobs/42/cancel")
+    );
+    assert_eq!(
+        Some(Scope::Read),
+        Scope::required(&Method::GET, "/api/v1/jobs/42")
+    );
+    assert_eq!(
+        Some(Scope::Admin),
+        Scope::required(&Method::DELETE, "/api/v1/jobs/42")
+    );
+    assert_eq!(
+        Some(Scope::Admin),
+        Scope::required(&Method::GET, "/api/v1/keys")
+    );
+    assert!(Scope::Admin.allows(Scope::Enqueue));
+    assert!(!Scope::Read.allows(Scope::Enqueue));
+    Ok(())
+}
@@ -0,0 +1,39 @@
+use std::convert::Infallible;
+
+use axum::{extract::FromRequestParts, http::request::Parts};
+
+pub use crate::models::DEFAULT_TENANT;
+
+use super::ApiKeyRow;
+
+/// Tenant of the API key of the request, every job, schedule and
+/// subscription query is scoped to it
+#[derive(Debug, Clone)]
+pub struct Tenant(pub String);
+
+impl Tenant {
+    /// The default tenant manages every tenant, the others only themselves
+    pub fn manages(&self, tenant_id: &str) -> bool {
+        self.0 == DEFAULT_TENANT || self.0 == tenant_id
+    }
+}
+
+pub fn is_valid_tenant_id(tenant_id: &str) -> bool {
+    !tenant_id.is_empty()
+        && tenant_id.len() <= 64
+        && tenant_id
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for Tenant {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let tenant_id = parts
+            .extensions
+            .get::<ApiKeyRow>()
+            .map_or(DEFAULT_TENANT, |api_key| api_key.tenant_id.as_str());
+        Ok(Tenant(tenant_id.to_string()))
+    }
+}
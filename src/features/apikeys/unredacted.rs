@@ -0,0 +1,44 @@
+use axum::{
+    extract::{FromRequestParts, Query},
+    http::request::Parts,
+};
+use problemdetails::Problem;
+use serde::Deserialize;
+
+use crate::models::Error;
+
+use super::{ApiKeyRow, Scope};
+
+#[derive(Deserialize)]
+struct UnredactedQuery {
+    #[serde(default)]
+    unredacted: bool,
+}
+
+/// `?unredacted=true` shows sensitive headers as stored, for keys with the admin scope
+#[derive(Debug, Clone, Copy)]
+pub struct Unredacted(pub bool);
+
+impl<S: Send + Sync> FromRequestParts<S> for Unredacted {
+    type Rejection = Problem;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let requested = Query::<UnredactedQuery>::try_from_uri(&parts.uri)
+            .map_err(|_| Error::InvalidParams("unredacted"))?
+            .unredacted;
+        if !requested {
+            return Ok(Unredacted(false));
+        }
+        // No key is only seen with --no-auth
+        let admin = parts.extensions.get::<ApiKeyRow>().is_none_or(|api_key| {
+            api_key
+                .scopes
+                .iter()
+                .any(|s| s.parse::<Scope>().is_ok_and(|s| s == Scope::Admin))
+        });
+        match admin {
+            true => Ok(Unredacted(true)),
+            false => Err(Error::Forbidden(Scope::Admin.as_str()).into()),
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use crate::{
+    features::apikeys::Tenant,
+    models::{AppState, JobEvent},
+};
+use axum::{
+    Router,
+    extract::{Query, State},
+    response::sse::{Event, KeepAlive, Sse},
+    routing::get,
+};
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+#[allow(unused_imports)]
+use tracing::{debug, error, info, warn};
+
+pub fn routes(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/events", get(events))
+        .with_state(state)
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EventsFilter {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    job_id: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    schedule_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    external_id: Option<String>,
+    /// Comma separated list of statuses
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<String>,
+}
+
+impl EventsFilter {
+    pub(crate) fn matches(&self, event: &JobEvent) -> bool {
+        if self.job_id.is_some_and(|id| id != event.id) {
+            return false;
+        }
+        if self
+            .schedule_id
+            .as_ref()
+            .is_some_and(|id| event.schedule_id.as_ref() != Some(id))
+        {
+            return false;
+        }
+        if self
+            .external_id
+            .as_ref()
+            .is_some_and(|id| event.external_id.as_ref() != Some(id))
+        {
+            return false;
+        }
+        if let Some(status) = &self.status {
+            return status.split(',').any(|s| s.trim() == event.status);
+        }
+        true
+    }
+}
+
+async fn events(
+    State(state): State<Arc<AppState>>,
+    Tenant(tenant_id): Tenant,
+    Query(filter): Query<EventsFilter>,
+) -> Sse<impl Stream<Item = Result<Event, axum::Error>>> {
+    let rx = state.notifier.subscribe_events();
+    let stream = futures::stream::unfold(
+        (rx, filter, tenant_id),
+        |(mut rx, filter, tenant_id)| async move {
+            loop {
+                match rx.recv().await {
+                    Ok(event) if event.tenant_id == tenant_id && filter.matches(&event) => {
+                        let sse = Event::default().event(&event.status).json_data(&event);
+                        return Some((sse, (rx, filter, tenant_id)));
+                    }
+                    Ok(_) => continue,
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!({ skipped }, "events lagged");
+                        continue;
+                    }
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        },
+    );
+    let stream = stream.take_until(state.shutdown_token.clone().cancelled_owned());
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+#[tokio::test]
+async fn events_filter_matches() -> anyhow::Result<()> {
+    // arrange
+    let event = JobEvent {
+        id: 42,
+        retry: 0,
+        instance_id: "host:01J".into(),
+        at: chrono::Utc::now(),
+        status: "failed".into(),
+        message: None,
+        schedule_id: Some("01JSCHEDULE".into()),
+        external_id: None,
+        subscription_id: None,
+        tenant_id: "default".into(),
+    };
+    let by_status = EventsFilter {
+        status: Some("completed,failed".into()),
+        ..Default::default()
+    };
+    let by_job = EventsFilter {
+        job_id: Some(7),
+        ..Default::default()
+    };
+    let by_external_id = EventsFilter {
+        external_id: Some("order-1".into()),
+        ..Default::default()
+    };
+
+    // act & assert
+    assert!(EventsFilter::default().matches(&event));
+    assert!(by_status.matches(&event));
+    assert!(!by_job.matches(&event));
+    assert!(!by_external_id.matches(&event));
+    Ok(())
+}
@@ -1,7 +1,11 @@
-use crate::models::{AppState, Error};
+use crate::{
+    features::apikeys::Tenant,
+    handlers::JobId,
+    models::{AppState, Error},
+};
 use axum::{
     Json, Router,
-    extract::{Path, Query, State},
+    extract::{Query, State},
     response::IntoResponse,
     routing::get,
 };
@@ -18,17 +22,22 @@ mod jobhistory;
 pub fn routes(state: Arc<AppState>) -> Router {
     Router::new()
         .route("/jobs/{id}/history", get(get_history_by_id))
+        .route(
+            "/jobs/by-external-id/{external_id}/history",
+            get(get_history_by_id),
+        )
         .with_state(state)
 }
 
 async fn get_history_by_id(
     State(state): State<Arc<AppState>>,
-    Path(id): Path<i64>,
+    Tenant(tenant_id): Tenant,
+    JobId(id): JobId,
     Query(pagination): Query<Paging>,
 ) -> Result<impl IntoResponse, Problem> {
     let limit = pagination.limit.unwrap_or(100);
     let offset = pagination.offset.unwrap_or(0);
-    let data = get_by_id(&state.pool, id, limit, offset).await?;
+    let data = get_by_id(&state.pool, &tenant_id, id, limit, offset).await?;
     Ok(Json(PagingResult {
         limit,
         offset,
@@ -38,15 +47,17 @@ async fn get_history_by_id(
 
 pub async fn get_by_id(
     pool: &Pool<Postgres>,
+    tenant_id: &str,
     job_id: i64,
     limit: i32,
     offset: i32,
 ) -> Result<Vec<JobHistoryRow>, Error> {
-    const SQL: &str = "SELECT id, retry, instance_id, status::text, at, message FROM history WHERE id = $1 ORDER BY at LIMIT $2 OFFSET $3";
+    const SQL: &str = "SELECT id, retry, instance_id, status::text, at, message FROM history WHERE id = $1 AND tenant_id = $4 ORDER BY at LIMIT $2 OFFSET $3";
     let history = sqlx::query_as::<_, JobHistoryRow>(SQL)
         .bind(job_id)
         .bind(limit)
         .bind(offset)
+        .bind(tenant_id)
         .fetch_all(pool)
         .await?;
     Ok(history)
@@ -1,11 +1,16 @@
 use serde::{Deserialize, Serialize};
 
+pub mod apikeys;
 pub mod echo;
+pub mod events;
 pub mod history;
 pub mod instances;
 pub mod live;
+pub mod quotas;
 pub mod results;
 pub mod schedules;
+pub mod secrets;
+pub mod subscriptions;
 
 #[derive(Deserialize)]
 pub struct Paging {
@@ -19,3 +24,17 @@ pub struct PagingResult<T> {
     offset: i32,
     data: Vec<T>,
 }
+
+/// Keyset paging, `cursor` is the last id of the previous page
+#[derive(Deserialize)]
+pub struct CursorPaging {
+    pub limit: Option<i32>,
+    pub cursor: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct CursorPagingResult<T> {
+    pub limit: i32,
+    pub next_cursor: Option<i64>,
+    pub data: Vec<T>,
+}
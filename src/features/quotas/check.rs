@@ -0,0 +1,25 @@
+use crate::models::{AppState, Error, JobCreate};
+
+use super::{Quota, db};
+
+/// Limits of the tenant over the defaults of the server
+pub async fn effective(app_state: &AppState, tenant_id: &str) -> Result<Quota, Error> {
+    let quota = db::get_by_tenant(&app_state.pool, tenant_id).await?;
+    Ok(quota.or(app_state.quota))
+}
+
+/// Rejects all of `jobs` when they would exceed a count limit, the counts are
+/// not locked so concurrent requests may overshoot a little
+pub async fn check_jobs(
+    app_state: &AppState,
+    tenant_id: &str,
+    quota: &Quota,
+    jobs: &[JobCreate],
+) -> Result<(), Error> {
+    if jobs.is_empty() || !quota.is_counted() {
+        return Ok(());
+    }
+    let usage = db::usage(&app_state.pool, tenant_id, quota).await?;
+    let schedules = jobs.iter().filter(|job| job.schedule.is_some()).count();
+    quota.check_usage(&usage, jobs.len() as i64, schedules as i64)
+}
@@ -0,0 +1,120 @@
+use crate::models::Error;
+
+use sqlx::{Pool, Postgres};
+
+use super::{Quota, QuotaUsage, UsageRow};
+
+/// Limits set for the tenant, the defaults of the server apply to the others
+pub async fn get_by_tenant(pool: &Pool<Postgres>, tenant_id: &str) -> Result<Quota, Error> {
+    const SQL: &str = "SELECT * FROM quotas WHERE tenant_id = $1";
+    let row = sqlx::query_as::<_, Quota>(SQL)
+        .bind(tenant_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(row.unwrap_or_default())
+}
+
+pub async fn upsert(pool: &Pool<Postgres>, tenant_id: &str, quota: Quota) -> Result<Quota, Error> {
+    const SQL: &str = "
+    INSERT INTO quotas(tenant_id, jobs_per_day, pending_jobs, schedules, max_body, max_retries)
+    VALUES ($1, $2, $3, $4, $5, $6)
+    ON CONFLICT (tenant_id) DO UPDATE SET
+        jobs_per_day = EXCLUDED.jobs_per_day,
+        pending_jobs = EXCLUDED.pending_jobs,
+        schedules = EXCLUDED.schedules,
+        max_body = EXCLUDED.max_body,
+        max_retries = EXCLUDED.max_retries,
+        updated_at = NOW()
+    RETURNING *
+    ";
+    let row = sqlx::query_as::<_, Quota>(SQL)
+        .bind(tenant_id)
+        .bind(quota.jobs_per_day)
+        .bind(quota.pending_jobs)
+        .bind(quota.schedules)
+        .bind(quota.max_body)
+        .bind(quota.max_retries)
+        .fetch_one(pool)
+        .await?;
+    Ok(row)
+}
+
+pub async fn delete(pool: &Pool<Postgres>, tenant_id: &str) -> Result<u64, Error> {
+    const SQL: &str = "DELETE FROM quotas WHERE tenant_id = $1";
+    let res = sqlx::query(SQL).bind(tenant_id).execute(pool).await?;
+    Ok(res.rows_affected())
+}
+
+/// Counts of the limits of `quota`, unlimited ones are not counted and stay 0
+pub async fn usage(
+    pool: &Pool<Postgres>,
+    tenant_id: &str,
+    quota: &Quota,
+) -> Result<QuotaUsage, Error> {
+    const SQL: &str = "
+    SELECT
+        CASE WHEN $2 THEN (
+            SELECT count(*) FROM jobs
+            WHERE tenant_id = $1 AND created_at >= date_trunc('day', now() AT TIME ZONE 'UTC') AT TIME ZONE 'UTC'
+        ) ELSE 0 END AS jobs_today,
+        CASE WHEN $3 THEN (
+            SELECT count(*) FROM scheduled s INNER JOIN jobs j ON j.id = s.id WHERE j.tenant_id = $1
+        ) + (
+            SELECT count(*) FROM enqueued e INNER JOIN jobs j ON j.id = e.id WHERE j.tenant_id = $1
+        ) ELSE 0 END AS pending_jobs,
+        CASE WHEN $4 THEN (
+            SELECT count(*) FROM schedules WHERE tenant_id = $1 AND NOT inactive
+        ) ELSE 0 END AS schedules
+    ";
+    let row = sqlx::query_as::<_, QuotaUsage>(SQL)
+        .bind(tenant_id)
+        .bind(quota.jobs_per_day.is_some())
+        .bind(quota.pending_jobs.is_some())
+        .bind(quota.schedules.is_some())
+        .fetch_one(pool)
+        .await?;
+    Ok(row)
+}
+
+/// Days without activity are left out
+pub async fn usage_by_day(
+    pool: &Pool<Postgres>,
+    tenant_id: &str,
+    from: chrono::NaiveDate,
+    to: chrono::NaiveDate,
+) -> Result<Vec<UsageRow>, Error> {
+    const SQL: &str = "
+    WITH r AS (
+        SELECT $2::date AT TIME ZONE 'UTC' AS from_at, ($3::date + 1) AT TIME ZONE 'UTC' AS to_at
+    ), h AS (
+        SELECT (h.at AT TIME ZONE 'UTC')::date AS day, count(*) AS enqueued
+        FROM history h, r
+        WHERE h.tenant_id = $1 AND h.status = 'enqueued' AND h.retry = 0 AND h.at >= r.from_at AND h.at < r.to_at
+        GROUP BY 1
+    ), p AS (
+        SELECT (p.at AT TIME ZONE 'UTC')::date AS day,
+            count(*) FILTER (WHERE p.status = 'completed') AS delivered,
+            count(*) FILTER (WHERE p.status = 'failed') AS failed,
+            COALESCE(sum((p.meta->>'request_bytes')::bigint), 0)::bigint AS request_bytes,
+            COALESCE(sum((p.meta->>'response_bytes')::bigint), 0)::bigint AS response_bytes
+        FROM processed p, r
+        WHERE p.tenant_id = $1 AND p.at >= r.from_at AND p.at < r.to_at
+        GROUP BY 1
+    )
+    SELECT COALESCE(h.day, p.day) AS day,
+        COALESCE(h.enqueued, 0) AS enqueued,
+        COALESCE(p.delivered, 0) AS delivered,
+        COALESCE(p.failed, 0) AS failed,
+        COALESCE(p.request_bytes, 0) AS request_bytes,
+        COALESCE(p.response_bytes, 0) AS response_bytes
+    FROM h FULL JOIN p ON p.day = h.day
+    ORDER BY 1
+    ";
+    let rows = sqlx::query_as::<_, UsageRow>(SQL)
+        .bind(tenant_id)
+        .bind(from)
+        .bind(to)
+        .fetch_all(pool)
+        .await?;
+    Ok(rows)
+}
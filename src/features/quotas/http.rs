@@ -0,0 +1,130 @@
+use crate::{
+    features::apikeys::{DEFAULT_TENANT, Tenant, is_valid_tenant_id},
+    models::{AppState, Error},
+};
+use axum::{
+    Json, Router,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use chrono::{Days, Utc};
+use problemdetails::Problem;
+use std::sync::Arc;
+
+use super::{Quota, QuotaStatus, UsageReport, UsageSearch, db};
+
+/// Default and max days of `/usage`
+const USAGE_DAYS: u64 = 30;
+const USAGE_MAX_DAYS: i64 = 366;
+
+pub fn routes(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/quotas", get(get_own))
+        .route(
+            "/quotas/{tenant_id}",
+            get(get_by_tenant).put(upsert).delete(delete),
+        )
+        .route("/usage", get(usage))
+        .with_state(state)
+}
+
+async fn status(state: &AppState, tenant_id: String) -> Result<QuotaStatus, Error> {
+    let quota = super::effective(state, &tenant_id).await?;
+    let usage = db::usage(&state.pool, &tenant_id, &quota).await?;
+    Ok(QuotaStatus {
+        tenant_id,
+        quota,
+        usage,
+    })
+}
+
+async fn get_own(
+    State(state): State<Arc<AppState>>,
+    Tenant(tenant_id): Tenant,
+) -> Result<impl IntoResponse, Problem> {
+    Ok(Json(status(&state, tenant_id).await?))
+}
+
+async fn get_by_tenant(
+    State(state): State<Arc<AppState>>,
+    caller: Tenant,
+    Path(tenant_id): Path<String>,
+) -> Result<impl IntoResponse, Problem> {
+    if !caller.manages(&tenant_id) {
+        return Err(Error::Forbidden("key of the tenant or the default tenant").into());
+    }
+    Ok(Json(status(&state, tenant_id).await?))
+}
+
+/// Only the default tenant sets limits, a tenant can not raise its own
+async fn upsert(
+    State(state): State<Arc<AppState>>,
+    Tenant(caller_tenant_id): Tenant,
+    Path(tenant_id): Path<String>,
+    Json(quota): Json<Quota>,
+) -> Result<impl IntoResponse, Problem> {
+    if caller_tenant_id != DEFAULT_TENANT {
+        return Err(Error::Forbidden("admin of the default tenant").into());
+    }
+    if !is_valid_tenant_id(&tenant_id) {
+        return Err(Error::InvalidParams("tenant_id").into());
+    }
+    let negative = [
+        quota.jobs_per_day,
+        quota.pending_jobs,
+        quota.schedules,
+        quota.max_body,
+    ]
+    .into_iter()
+    .flatten()
+    .any(|n| n < 0);
+    if negative || quota.max_retries.is_some_and(|n| n < 0) {
+        return Err(Error::InvalidParams("quota").into());
+    }
+    let row = db::upsert(&state.pool, &tenant_id, quota).await?;
+    Ok(Json(row))
+}
+
+async fn delete(
+    State(state): State<Arc<AppState>>,
+    Tenant(caller_tenant_id): Tenant,
+    Path(tenant_id): Path<String>,
+) -> Result<Response, Problem> {
+    if caller_tenant_id != DEFAULT_TENANT {
+        return Err(Error::Forbidden("admin of the default tenant").into());
+    }
+    let rows = db::delete(&state.pool, &tenant_id).await?;
+    match rows {
+        0 => Ok(StatusCode::NOT_FOUND.into_response()),
+        _ => Ok(StatusCode::NO_CONTENT.into_response()),
+    }
+}
+
+async fn usage(
+    State(state): State<Arc<AppState>>,
+    caller: Tenant,
+    Query(search): Query<UsageSearch>,
+) -> Result<impl IntoResponse, Problem> {
+    let tenant_id = search.tenant_id.unwrap_or_else(|| caller.0.clone());
+    if !caller.manages(&tenant_id) {
+        return Err(Error::Forbidden("key of the tenant or the default tenant").into());
+    }
+    let to = search.to.unwrap_or_else(|| Utc::now().date_naive());
+    let from = search
+        .from
+        .or_else(|| to.checked_sub_days(Days::new(USAGE_DAYS - 1)))
+        .ok_or(Error::InvalidParams("from"))?;
+    let days = (to - from).num_days();
+    if !(0..USAGE_MAX_DAYS).contains(&days) {
+        return Err(Error::InvalidParams("from").into());
+    }
+    let data = db::usage_by_day(&state.pool, &tenant_id, from, to).await?;
+    Ok(Json(UsageReport {
+        tenant_id,
+        from,
+        to,
+        data,
+    }))
+}
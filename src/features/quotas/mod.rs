@@ -0,0 +1,10 @@
+pub use check::{check_jobs, effective};
+pub use http::routes;
+pub use quota::{Quota, QuotaStatus, QuotaUsage};
+pub use usage_row::{UsageReport, UsageRow, UsageSearch};
+
+mod check;
+mod db;
+mod http;
+mod quota;
+mod usage_row;
@@ -0,0 +1,122 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::{Error, JobCreate};
+
+/// Limits of a tenant, `None` is unlimited
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, sqlx::FromRow, Serialize, Deserialize)]
+pub struct Quota {
+    /// Jobs created per UTC day
+    pub jobs_per_day: Option<i64>,
+    /// Scheduled and enqueued jobs
+    pub pending_jobs: Option<i64>,
+    /// Active schedules
+    pub schedules: Option<i64>,
+    /// Request body of a job in bytes
+    pub max_body: Option<i64>,
+    /// Retries of a job after the first attempt
+    pub max_retries: Option<i32>,
+}
+
+/// Current counts of the limits of `Quota`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, sqlx::FromRow, Serialize)]
+pub struct QuotaUsage {
+    pub jobs_today: i64,
+    pub pending_jobs: i64,
+    pub schedules: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QuotaStatus {
+    pub tenant_id: String,
+    pub quota: Quota,
+    pub usage: QuotaUsage,
+}
+
+impl Quota {
+    /// Limits of a tenant override the server defaults one by one
+    pub fn or(self, defaults: Quota) -> Quota {
+        Quota {
+            jobs_per_day: self.jobs_per_day.or(defaults.jobs_per_day),
+            pending_jobs: self.pending_jobs.or(defaults.pending_jobs),
+            schedules: self.schedules.or(defaults.schedules),
+            max_body: self.max_body.or(defaults.max_body),
+            max_retries: self.max_retries.or(defaults.max_retries),
+        }
+    }
+
+    /// Limits that need a count of existing jobs or schedules
+    pub fn is_counted(&self) -> bool {
+        self.jobs_per_day.is_some() || self.pending_jobs.is_some() || self.schedules.is_some()
+    }
+
+    /// Body and retries of a job, the body is checked before compression
+    pub fn check_job(&self, job: &JobCreate) -> Result<(), Error> {
+        if let Some(max) = self.max_body
+            && job.body.len() as i64 > max
+        {
+            return Err(Error::QuotaExceeded("max_body", max));
+        }
+        if let Some(max) = self.max_retries
+            && i32::from(job.meta.retry.retry_count()) > max
+        {
+            return Err(Error::QuotaExceeded("max_retries", max.into()));
+        }
+        Ok(())
+    }
+
+    /// Room for `jobs` more jobs of which `schedules` start a schedule
+    pub fn check_usage(&self, usage: &QuotaUsage, jobs: i64, schedules: i64) -> Result<(), Error> {
+        if let Some(max) = self.jobs_per_day
+            && usage.jobs_today + jobs > max
+        {
+            return Err(Error::QuotaExceeded("jobs_per_day", max));
+        }
+        if let Some(max) = self.pending_jobs
+            && usage.pending_jobs + jobs > max
+        {
+            return Err(Error::QuotaExceeded("pending_jobs", max));
+        }
+        if let Some(max) = self.schedules
+            && schedules > 0
+            && usage.schedules + schedules > max
+        {
+            return Err(Error::QuotaExceeded("schedules", max));
+        }
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn quota_check_usage() -> anyhow::Result<()> {
+    // arrange
+    let defaults = Quota {
+        jobs_per_day: Some(100),
+        schedules: Some(2),
+        ..Default::default()
+    };
+    let quota = Quota {
+        jobs_per_day: Some(10),
+        ..Default::default()
+    }
+    .or(defaults);
+    let usage = QuotaUsage {
+        jobs_today: 9,
+        pending_jobs: 50,
+        schedules: 2,
+    };
+
+    // act & assert
+    assert_eq!(Some(10), quota.jobs_per_day);
+    assert!(quota.is_counted());
+    assert!(quota.check_usage(&usage, 1, 0).is_ok());
+    assert!(matches!(
+        quota.check_usage(&usage, 2, 0),
+        Err(Error::QuotaExceeded("jobs_per_day", 10))
+    ));
+    assert!(matches!(
+        quota.check_usage(&usage, 1, 1),
+        Err(Error::QuotaExceeded("schedules", 2))
+    ));
+    assert!(!Quota::default().is_counted());
+    Ok(())
+}
@@ -0,0 +1,31 @@
+use serde::{Deserialize, Serialize};
+
+/// Activity of a tenant on a UTC day
+#[derive(Debug, Clone, sqlx::FromRow, Serialize)]
+pub struct UsageRow {
+    pub day: chrono::NaiveDate,
+    /// Jobs enqueued for their first attempt, retries are not counted
+    pub enqueued: i64,
+    pub delivered: i64,
+    pub failed: i64,
+    /// Body bytes of the last attempt of processed jobs
+    pub request_bytes: i64,
+    pub response_bytes: i64,
+}
+
+/// Inclusive range of UTC days, the last 30 days by default
+#[derive(Debug, Clone, Deserialize)]
+pub struct UsageSearch {
+    pub from: Option<chrono::NaiveDate>,
+    pub to: Option<chrono::NaiveDate>,
+    /// Tenant of the caller when not set
+    pub tenant_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UsageReport {
+    pub tenant_id: String,
+    pub from: chrono::NaiveDate,
+    pub to: chrono::NaiveDate,
+    pub data: Vec<UsageRow>,
+}
@@ -1,31 +1,95 @@
 use crate::{
-    features::results::job_result::{JobResultRow, JobResultType},
-    models::Error,
+    features::results::job_result::{JobResultMeta, JobResultRow, JobResultType},
+    models::{BodyEncoding, Error, JobEvent, PROCESSED_CHANNEL},
 };
-use sqlx::{Pool, Postgres, types::Json};
+use sqlx::{PgExecutor, Pool, Postgres, types::Json};
 
 use super::JobResult;
 
-pub async fn get_by_id(pool: &Pool<Postgres>, job_id: i64) -> Result<Option<JobResultRow>, Error> {
-    const SQL: &str = "SELECT * FROM processed WHERE id = $1";
+pub async fn get_by_id(
+    pool: &Pool<Postgres>,
+    tenant_id: &str,
+    job_id: i64,
+) -> Result<Option<JobResultRow>, Error> {
+    const SQL: &str = "SELECT * FROM processed WHERE id = $1 AND tenant_id = $2";
     let job = sqlx::query_as::<_, JobResultRow>(SQL)
         .bind(job_id)
+        .bind(tenant_id)
         .fetch_optional(pool)
         .await?;
     Ok(job)
 }
 
+/// Outcome of `cancel`
+#[derive(Debug, Clone, PartialEq)]
+pub enum CancelOutcome {
+    NotFound,
+    /// Assigned or already processed
+    Conflict,
+    Cancelled {
+        schedule_id: Option<String>,
+        /// Time the job was scheduled at, `None` when it was enqueued
+        at: Option<i64>,
+    },
+}
+
+/// Cancels a job that is scheduled or enqueued but not yet assigned
+pub async fn cancel(
+    conn: impl PgExecutor<'_>,
+    tenant_id: &str,
+    job_id: i64,
+    instance_id: &str,
+) -> Result<CancelOutcome, Error> {
+    const SQL: &str = "WITH j AS (
+        SELECT id, schedule_id, tenant_id FROM jobs WHERE id = $1 AND tenant_id = $5
+    ), a AS (
+        DELETE FROM scheduled WHERE id IN (SELECT id FROM j) RETURNING id, retry, at
+    ), b AS (
+        DELETE FROM enqueued WHERE id IN (SELECT id FROM j) AND lock_at IS NULL RETURNING id, retry
+    ), c AS (
+        SELECT id, retry FROM a UNION ALL SELECT id, retry FROM b
+    ), hist AS (
+        INSERT INTO history(id, retry, instance_id, at, status, tenant_id) SELECT id, retry, $2 as instance_id, now() as at, 'cancelled'::history_status as status, $5 as tenant_id FROM c RETURNING id
+    ), p AS (
+        INSERT INTO processed(id, retry, instance_id, at, status, meta, tenant_id) SELECT id, retry, $2 as instance_id, now() as at, 'cancelled'::processed_status as status, $3 as meta, $5 as tenant_id FROM c RETURNING id
+    )
+    SELECT j.schedule_id, (SELECT at FROM a) as at, (SELECT count(pg_notify($4, id::text)) FROM p) as cancelled FROM j";
+    let meta = JobResultMeta {
+        result: JobResultType::Cancelled,
+        ..Default::default()
+    };
+    let row = sqlx::query_as::<_, (Option<String>, Option<i64>, i64)>(SQL)
+        .bind(job_id)
+        .bind(instance_id)
+        .bind(Json(meta))
+        .bind(PROCESSED_CHANNEL)
+        .bind(tenant_id)
+        .fetch_optional(conn)
+        .await?;
+    Ok(match row {
+        None => CancelOutcome::NotFound,
+        Some((_, _, 0)) => CancelOutcome::Conflict,
+        Some((schedule_id, at, _)) => CancelOutcome::Cancelled { schedule_id, at },
+    })
+}
+
 pub async fn processed(
     pool: &Pool<Postgres>,
     job_id: i64,
     job_result: JobResult,
+    encoding: BodyEncoding,
 ) -> Result<u64, Error> {
     const SQL: &str = "WITH a AS (
         DELETE FROM enqueued WHERE id = $1 RETURNING id, retry, instance_id
+    ), t AS (
+        SELECT a.*, j.tenant_id FROM a INNER JOIN jobs j ON j.id = a.id
     ), hist AS (
-        INSERT INTO history SELECT id, retry, instance_id, now() as at, $2::history_status as status FROM a RETURNING id
+        INSERT INTO history(id, retry, instance_id, at, status, message, tenant_id) SELECT id, retry, instance_id, now() as at, $2::history_status as status, $10 as message, tenant_id FROM t RETURNING id
+    ), p AS (
+        INSERT INTO processed(id, retry, instance_id, at, status, meta, headers, body, body_ref, body_codec, envelope, tenant_id)
+        SELECT id, retry, instance_id, now() as at, $2::processed_status as status, $3 as meta, $4 as headers, $5 as body, $7 as body_ref, $8 as body_codec, $9 as envelope, tenant_id FROM t RETURNING id
     )
-    INSERT INTO processed SELECT id, retry, instance_id, now() as at, $2::processed_status as status, $3 as meta, $4 as headers, $5 as body FROM a RETURNING id";
+    SELECT pg_notify($6, id::text) FROM p";
     let body: Option<&[u8]> = match job_result.body.is_empty() {
         true => None,
         false => Some(job_result.body.as_ref()),
@@ -40,12 +104,21 @@ pub async fn processed(
         }
         _ => "completed",
     };
+    let message = job_result
+        .meta
+        .dead_lettered
+        .then_some(JobEvent::DEAD_LETTERED);
     let res = sqlx::query(SQL)
         .bind(job_id)
         .bind(status)
         .bind(Json(job_result.meta))
         .bind(Json(job_result.headers))
         .bind(body)
+        .bind(PROCESSED_CHANNEL)
+        .bind(encoding.body_ref)
+        .bind(encoding.codec.map(|c| c.to_string()))
+        .bind(encoding.envelope.map(Json))
+        .bind(message)
         .execute(pool)
         .await?;
     Ok(res.rows_affected())
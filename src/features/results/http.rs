@@ -1,45 +1,84 @@
-use crate::models::AppState;
+use crate::{
+    features::apikeys::{Tenant, Unredacted},
+    handlers::JobId,
+    models::{AppState, Error, parse_duration_secs},
+};
 use axum::{
     Json, Router,
-    extract::{Path, State},
+    extract::{Query, State},
     http::StatusCode,
     response::{IntoResponse, Response},
     routing::get,
 };
 use problemdetails::Problem;
+use serde::Deserialize;
 use std::sync::Arc;
 
-use super::JobResult;
+use super::{JobResult, wait::get_result, wait::wait_result, wait::wait_timeout};
 
 pub fn routes(state: Arc<AppState>) -> Router {
     Router::new()
         .route("/jobs/{id}/result", get(result_by_id))
         .route("/jobs/{id}/result/raw", get(result_by_id_raw))
+        .route(
+            "/jobs/by-external-id/{external_id}/result",
+            get(result_by_id),
+        )
+        .route(
+            "/jobs/by-external-id/{external_id}/result/raw",
+            get(result_by_id_raw),
+        )
         .with_state(state)
 }
 
+#[derive(Deserialize)]
+struct WaitQuery {
+    wait: Option<String>,
+}
+
 async fn result_by_id(
     State(state): State<Arc<AppState>>,
-    Path(id): Path<i64>,
+    Tenant(tenant_id): Tenant,
+    Unredacted(unredacted): Unredacted,
+    JobId(id): JobId,
+    Query(query): Query<WaitQuery>,
 ) -> Result<Response, Problem> {
-    let job_result = super::db::get_by_id(&state.pool, id)
-        .await?
-        .map(JobResult::from);
+    let job_result = get_or_wait(&state, &tenant_id, id, query).await?;
     match job_result {
         None => Ok(StatusCode::NO_CONTENT.into_response()),
-        Some(o) => Ok(Json(o).into_response()),
+        Some(mut o) => {
+            if !unredacted {
+                state.redaction.redact(&mut o.headers);
+            }
+            Ok(Json(o).into_response())
+        }
     }
 }
 
 async fn result_by_id_raw(
     State(state): State<Arc<AppState>>,
-    Path(id): Path<i64>,
+    Tenant(tenant_id): Tenant,
+    JobId(id): JobId,
+    Query(query): Query<WaitQuery>,
 ) -> Result<Response, Problem> {
-    let job_result = super::db::get_by_id(&state.pool, id)
-        .await?
-        .map(JobResult::from);
+    let job_result = get_or_wait(&state, &tenant_id, id, query).await?;
     match job_result {
         None => Ok(StatusCode::NO_CONTENT.into_response()),
         Some(o) => Ok(o.into_response()),
     }
 }
+
+async fn get_or_wait(
+    state: &AppState,
+    tenant_id: &str,
+    id: i64,
+    query: WaitQuery,
+) -> Result<Option<JobResult>, Error> {
+    match query.wait {
+        Some(wait) => {
+            let secs = parse_duration_secs(&wait).map_err(|_| Error::InvalidParams("wait"))?;
+            wait_result(state, tenant_id, id, wait_timeout(secs)).await
+        }
+        None => get_result(state, tenant_id, id).await,
+    }
+}
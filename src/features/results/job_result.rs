@@ -6,7 +6,7 @@ use bytes::Bytes;
 use hyper::{HeaderMap, StatusCode};
 use serde::{Deserialize, Serialize};
 
-use crate::models::Error;
+use crate::models::{Envelope, Error};
 
 #[derive(Debug, Clone, Default, Serialize)]
 pub struct JobResult {
@@ -20,9 +20,13 @@ pub struct JobResultRow {
     pub id: i64,
     #[sqlx(json)]
     pub meta: JobResultMeta,
-    #[sqlx(json)]
+    #[sqlx(json(nullable))]
     pub headers: Option<HashMap<String, String>>,
     pub body: Option<Vec<u8>>,
+    pub body_ref: Option<String>,
+    pub body_codec: Option<String>,
+    #[sqlx(json(nullable))]
+    pub envelope: Option<Envelope>,
 }
 
 impl From<JobResultRow> for JobResult {
@@ -39,6 +43,22 @@ impl From<JobResultRow> for JobResult {
 pub struct JobResultMeta {
     #[serde(flatten, default)]
     pub result: JobResultType,
+    /// The response body was cut at the capture limit
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+    /// Request body sent to the destination, for usage accounting
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub request_bytes: u32,
+    /// Response body read from the destination, for usage accounting
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub response_bytes: u32,
+    /// Failed with the retry policy exhausted
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub dead_lettered: bool,
+}
+
+fn is_zero(n: &u32) -> bool {
+    *n == 0
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
@@ -72,6 +92,7 @@ impl JobResult {
         version: hyper::Version,
         headers: Option<HashMap<String, String>>,
         body: Bytes,
+        truncated: bool,
     ) -> JobResult {
         JobResult {
             meta: JobResultMeta {
@@ -79,6 +100,8 @@ impl JobResult {
                     status_code,
                     version,
                 }),
+                truncated,
+                ..Default::default()
             },
             headers,
             body,
@@ -87,13 +110,28 @@ impl JobResult {
 
     fn with_type(result: JobResultType) -> JobResult {
         JobResult {
-            meta: JobResultMeta { result },
+            meta: JobResultMeta {
+                result,
+                ..Default::default()
+            },
             headers: None,
             body: Bytes::new(),
         }
     }
 }
 
+/// Message with its causes, a blocked destination is only named by the cause
+fn error_message(err: &Error) -> String {
+    let mut message = err.to_string();
+    let mut source = std::error::Error::source(err);
+    while let Some(err) = source {
+        message.push_str(": ");
+        message.push_str(&err.to_string());
+        source = err.source();
+    }
+    message
+}
+
 impl From<Error> for JobResult {
     fn from(value: Error) -> Self {
         match value {
@@ -101,7 +139,7 @@ impl From<Error> for JobResult {
             Error::ClientError(res) => res,
             Error::ServerError(res) => res,
             _ => JobResult::with_type(JobResultType::Error {
-                error: value.to_string(),
+                error: error_message(&value),
             }),
         }
     }
@@ -118,10 +156,12 @@ impl IntoResponse for JobResult {
                 let map = HeaderMap::try_from(&headers).unwrap();
                 let headers_mut = response.headers_mut();
                 for (key, value) in map {
-                    if let Some(key) = key {
-                        if key.to_string().starts_with("content") {
-                            headers_mut.insert(key, value);
-                        }
+                    // Content-Length follows the stored, possibly truncated body
+                    if let Some(key) = key
+                        && key.as_str().starts_with("content")
+                        && key != hyper::header::CONTENT_LENGTH
+                    {
+                        headers_mut.insert(key, value);
                     }
                 }
             };
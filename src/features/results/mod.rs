@@ -1,7 +1,9 @@
-pub use db::processed;
+pub use db::{CancelOutcome, cancel, processed};
 pub use http::routes;
 pub use job_result::JobResult;
+pub use wait::{wait_result, wait_timeout};
 
 mod db;
 mod http;
 mod job_result;
+mod wait;
@@ -0,0 +1,66 @@
+use std::time::Duration;
+
+use tokio::{sync::broadcast::error::RecvError, time};
+
+use crate::models::{AppState, Error};
+
+use super::JobResult;
+
+/// Upper bound for `?wait=` and `_sync` in seconds
+pub const MAX_WAIT_SECS: u32 = 300;
+
+/// Waits until the job is processed or `timeout` elapses
+pub async fn wait_result(
+    app_state: &AppState,
+    tenant_id: &str,
+    job_id: i64,
+    timeout: Duration,
+) -> Result<Option<JobResult>, Error> {
+    // Subscribe before the first lookup so a completion in between is not missed
+    let mut rx = app_state.notifier.subscribe();
+    let wait = async {
+        loop {
+            if let Some(job_result) = get_result(app_state, tenant_id, job_id).await? {
+                return Ok(Some(job_result));
+            }
+            loop {
+                match rx.recv().await {
+                    Ok(id) if id == job_id => break,
+                    Ok(_) => continue,
+                    Err(RecvError::Lagged(_)) => break,
+                    Err(RecvError::Closed) => return Ok(None),
+                }
+            }
+        }
+    };
+    match time::timeout(timeout, wait).await {
+        Ok(res) => res,
+        Err(_) => Ok(None),
+    }
+}
+
+/// Processed result with the body loaded from the blob store and decompressed
+pub async fn get_result(
+    app_state: &AppState,
+    tenant_id: &str,
+    job_id: i64,
+) -> Result<Option<JobResult>, Error> {
+    let Some(mut row) = super::db::get_by_id(&app_state.pool, tenant_id, job_id).await? else {
+        return Ok(None);
+    };
+    app_state
+        .body_options
+        .decode(
+            &mut row.body,
+            &mut row.headers,
+            row.body_ref.as_deref(),
+            &mut row.body_codec,
+            &mut row.envelope,
+        )
+        .await?;
+    Ok(Some(JobResult::from(row)))
+}
+
+pub fn wait_timeout(secs: u32) -> Duration {
+    Duration::from_secs(secs.min(MAX_WAIT_SECS).into())
+}
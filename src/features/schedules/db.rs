@@ -1,53 +1,89 @@
 use crate::models::Error;
 
-use sqlx::{Pool, Postgres};
+use sqlx::{PgExecutor, Pool, Postgres};
 
-use super::ScheduleRow;
+use super::{JobSchedule, ScheduleRow};
 
 pub async fn get_by_id(
     pool: &Pool<Postgres>,
+    tenant_id: &str,
     schedule_id: &str,
 ) -> Result<Option<ScheduleRow>, Error> {
-    const SQL: &str = "SELECT * FROM schedules WHERE schedule_id = $1";
+    const SQL: &str = "SELECT * FROM schedules WHERE schedule_id = $1 AND tenant_id = $2";
     let row = sqlx::query_as::<_, ScheduleRow>(SQL)
         .bind(schedule_id)
+        .bind(tenant_id)
         .fetch_optional(pool)
         .await?;
     Ok(row)
 }
 
+/// Next occurrence of an active schedule after `after`, `None` when it is inactive, removed or past `until`
+pub async fn next_at(
+    pool: &Pool<Postgres>,
+    tenant_id: &str,
+    schedule_id: &str,
+    after: i64,
+) -> Result<Option<i64>, Error> {
+    let Some(row) = get_by_id(pool, tenant_id, schedule_id).await? else {
+        return Ok(None);
+    };
+    if row.inactive {
+        return Ok(None);
+    }
+    let schedule = row.schedule.parse::<JobSchedule>()?;
+    let offset = schedule.anchor_offset(row.anchor)
+        + row.jitter.map_or(0, |j| {
+            JobSchedule::jitter_offset(schedule_id, j.try_into().unwrap_or_default())
+        });
+    Ok(schedule.next_with_offset(after, row.until, offset))
+}
+
 pub async fn get_all(
     pool: &Pool<Postgres>,
+    tenant_id: &str,
     limit: i32,
     offset: i32,
 ) -> Result<Vec<ScheduleRow>, Error> {
-    const SQL: &str = "SELECT * FROM schedules ORDER BY schedule_id LIMIT $1 OFFSET $2;";
+    const SQL: &str =
+        "SELECT * FROM schedules WHERE tenant_id = $3 ORDER BY schedule_id LIMIT $1 OFFSET $2;";
     let res = sqlx::query_as::<_, ScheduleRow>(SQL)
         .bind(limit)
         .bind(offset)
+        .bind(tenant_id)
         .fetch_all(pool)
         .await?;
     Ok(res)
 }
 
 pub async fn inactive(
-    pool: &Pool<Postgres>,
+    conn: impl PgExecutor<'_>,
+    tenant_id: &str,
     schedule_id: &str,
     inactive: bool,
 ) -> Result<u64, Error> {
     const SQL: &str = "
-    UPDATE schedules SET inactive = $2 WHERE schedule_id = $1 RETURNING schedule_id
+    UPDATE schedules SET inactive = $2 WHERE schedule_id = $1 AND tenant_id = $3 RETURNING schedule_id
     ";
     let res = sqlx::query(SQL)
         .bind(schedule_id)
         .bind(inactive)
-        .execute(pool)
+        .bind(tenant_id)
+        .execute(conn)
         .await?;
     Ok(res.rows_affected())
 }
 
-pub async fn delete(pool: &Pool<Postgres>, schedule_id: &str) -> Result<u64, Error> {
-    const SQL: &str = "DELETE FROM schedules WHERE schedule_id = $1";
-    let res = sqlx::query(SQL).bind(schedule_id).execute(pool).await?;
+pub async fn delete(
+    pool: &Pool<Postgres>,
+    tenant_id: &str,
+    schedule_id: &str,
+) -> Result<u64, Error> {
+    const SQL: &str = "DELETE FROM schedules WHERE schedule_id = $1 AND tenant_id = $2";
+    let res = sqlx::query(SQL)
+        .bind(schedule_id)
+        .bind(tenant_id)
+        .execute(pool)
+        .await?;
     Ok(res.rows_affected())
 }
@@ -1,5 +1,5 @@
 use crate::{
-    features::{Paging, PagingResult},
+    features::{Paging, PagingResult, apikeys::Tenant},
     models::AppState,
 };
 use axum::{
@@ -24,10 +24,12 @@ pub fn routes(state: Arc<AppState>) -> Router {
 
 async fn get_all(
     State(state): State<Arc<AppState>>,
+    Tenant(tenant_id): Tenant,
     Query(pagination): Query<Paging>,
 ) -> Result<impl IntoResponse, Problem> {
     let result = super::db::get_all(
         &state.pool,
+        &tenant_id,
         pagination.limit.unwrap_or(10),
         pagination.offset.unwrap_or(0),
     )
@@ -41,9 +43,10 @@ async fn get_all(
 
 async fn get_by_id(
     State(state): State<Arc<AppState>>,
+    Tenant(tenant_id): Tenant,
     Path(schedule_id): Path<String>,
 ) -> Result<Response, Problem> {
-    let job_result = super::db::get_by_id(&state.pool, &schedule_id).await?;
+    let job_result = super::db::get_by_id(&state.pool, &tenant_id, &schedule_id).await?;
     match job_result {
         None => Ok(StatusCode::NO_CONTENT.into_response()),
         Some(o) => Ok(Json(o).into_response()),
@@ -52,9 +55,10 @@ async fn get_by_id(
 
 async fn inactive(
     State(state): State<Arc<AppState>>,
+    Tenant(tenant_id): Tenant,
     Path(schedule_id): Path<String>,
 ) -> Result<Response, Problem> {
-    let rows = super::db::inactive(&state.pool, &schedule_id, true).await?;
+    let rows = super::db::inactive(&state.pool, &tenant_id, &schedule_id, true).await?;
     match rows {
         0 => Ok(StatusCode::NOT_FOUND.into_response()),
         _ => Ok(StatusCode::NO_CONTENT.into_response()),
@@ -63,9 +67,10 @@ async fn inactive(
 
 async fn delete(
     State(state): State<Arc<AppState>>,
+    Tenant(tenant_id): Tenant,
     Path(schedule_id): Path<String>,
 ) -> Result<Response, Problem> {
-    let rows = super::db::delete(&state.pool, &schedule_id).await?;
+    let rows = super::db::delete(&state.pool, &tenant_id, &schedule_id).await?;
     match rows {
         0 => Ok(StatusCode::NOT_FOUND.into_response()),
         _ => Ok(StatusCode::NO_CONTENT.into_response()),
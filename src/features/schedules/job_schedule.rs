@@ -46,6 +46,38 @@ impl JobSchedule {
             _ => next,
         }
     }
+
+    /// Next occurrence shifted by a fixed `offset`, so that every occurrence is `base + offset`
+    pub fn next_with_offset(
+        &self,
+        after_unix_sec: i64,
+        until_unix_sec: Option<i64>,
+        offset: i64,
+    ) -> Option<i64> {
+        self.next(after_unix_sec - offset, until_unix_sec.map(|u| u - offset))
+            .map(|n| n + offset)
+    }
+
+    /// Phase offset of an interval anchored at `anchor_unix_sec`, `0` for epoch-aligned and cron schedules
+    pub fn anchor_offset(&self, anchor_unix_sec: Option<i64>) -> i64 {
+        match (self, anchor_unix_sec) {
+            (JobSchedule::Interval { interval }, Some(anchor)) if *interval > 0 => {
+                anchor.rem_euclid(i64::from(*interval))
+            }
+            _ => 0,
+        }
+    }
+
+    /// Stable pseudo-random offset in `[0, window)` seconds derived from `seed` (FNV-1a)
+    pub fn jitter_offset(seed: &str, window: u32) -> i64 {
+        if window == 0 {
+            return 0;
+        }
+        let hash = seed.bytes().fold(0xcbf29ce484222325_u64, |hash, b| {
+            (hash ^ u64::from(b)).wrapping_mul(0x100000001b3)
+        });
+        (hash % u64::from(window)) as i64
+    }
 }
 
 impl Default for JobSchedule {
@@ -192,3 +224,69 @@ async fn schedule_from_str_cron_after() -> anyhow::Result<()> {
     assert_eq!(now_sec_to_5_min + 300, next.unwrap());
     Ok(())
 }
+
+#[tokio::test]
+async fn schedule_jitter_offset_stable() -> anyhow::Result<()> {
+    // arrange
+    let schedule_id = "01HZX3J5Q7V8K2M4N6P8R0T2W4";
+
+    // act
+    let offset = JobSchedule::jitter_offset(schedule_id, 600);
+
+    // assert
+    assert!((0..600).contains(&offset));
+    assert_eq!(offset, JobSchedule::jitter_offset(schedule_id, 600));
+    assert_eq!(0, JobSchedule::jitter_offset(schedule_id, 0));
+    Ok(())
+}
+
+#[tokio::test]
+async fn schedule_interval_next_with_offset() -> anyhow::Result<()> {
+    // arrange
+    let schedule = JobSchedule::Interval { interval: 3600 };
+    let offset = 120;
+
+    // act
+    let first = schedule.next_with_offset(7200, None, offset);
+    let second = schedule.next_with_offset(first.unwrap(), None, offset);
+    let until = schedule.next_with_offset(7200, Some(7300), offset);
+
+    // assert
+    assert_eq!(Some(7200 + offset), first);
+    assert_eq!(Some(10800 + offset), second);
+    assert!(until.is_none());
+    Ok(())
+}
+
+#[tokio::test]
+async fn schedule_interval_anchored_to_created() -> anyhow::Result<()> {
+    // arrange
+    let schedule = JobSchedule::Interval {
+        interval: 7 * 60 * 60,
+    };
+    let created = 1_700_000_000 + 10 * 60 * 60 + 13 * 60;
+
+    // act
+    let offset = schedule.anchor_offset(Some(created));
+    let first = schedule.next_with_offset(created, None, offset);
+    let second = schedule.next_with_offset(first.unwrap(), None, offset);
+
+    // assert
+    assert_eq!(Some(created + 7 * 60 * 60), first);
+    assert_eq!(Some(created + 14 * 60 * 60), second);
+    Ok(())
+}
+
+#[tokio::test]
+async fn schedule_anchor_offset_epoch_and_cron() -> anyhow::Result<()> {
+    // arrange
+    let interval = JobSchedule::Interval { interval: 300 };
+    let cron: JobSchedule = "*/5 * * * *".parse().unwrap();
+
+    // act & assert
+    assert_eq!(0, interval.anchor_offset(None));
+    assert_eq!(0, interval.anchor_offset(Some(600)));
+    assert_eq!(20, interval.anchor_offset(Some(620)));
+    assert_eq!(0, cron.anchor_offset(Some(620)));
+    Ok(())
+}
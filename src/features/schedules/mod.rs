@@ -1,4 +1,4 @@
-pub use db::get_by_id;
+pub use db::{inactive, next_at};
 pub use http::routes;
 pub use job_schedule::JobSchedule;
 pub use schedule_row::ScheduleRow;
@@ -7,5 +7,7 @@ pub struct ScheduleRow {
     pub until: Option<i64>,
     pub next_id: Option<i64>,
     pub next_at: Option<i64>,
+    pub jitter: Option<i32>,
+    pub anchor: Option<i64>,
     pub inactive: bool,
 }
@@ -0,0 +1,115 @@
+use crate::models::{Envelope, Error};
+
+use sqlx::{Pool, Postgres, types::Json};
+
+use super::SecretRow;
+
+pub async fn upsert(
+    pool: &Pool<Postgres>,
+    tenant_id: &str,
+    name: &str,
+    value: &[u8],
+    envelope: &Envelope,
+    hosts: &[String],
+) -> Result<SecretRow, Error> {
+    const SQL: &str = "
+    INSERT INTO secrets(tenant_id, name, value, envelope, hosts) VALUES ($1, $2, $3, $4, $5)
+    ON CONFLICT (tenant_id, name) DO UPDATE SET
+        value = EXCLUDED.value,
+        envelope = EXCLUDED.envelope,
+        hosts = EXCLUDED.hosts,
+        updated_at = NOW()
+    RETURNING *
+    ";
+    let row = sqlx::query_as::<_, SecretRow>(SQL)
+        .bind(tenant_id)
+        .bind(name)
+        .bind(value)
+        .bind(Json(envelope))
+        .bind(hosts)
+        .fetch_one(pool)
+        .await?;
+    Ok(row)
+}
+
+pub async fn get_by_name(
+    pool: &Pool<Postgres>,
+    tenant_id: &str,
+    name: &str,
+) -> Result<Option<SecretRow>, Error> {
+    const SQL: &str = "SELECT * FROM secrets WHERE tenant_id = $1 AND name = $2";
+    let row = sqlx::query_as::<_, SecretRow>(SQL)
+        .bind(tenant_id)
+        .bind(name)
+        .fetch_optional(pool)
+        .await?;
+    Ok(row)
+}
+
+pub async fn get_by_names(
+    pool: &Pool<Postgres>,
+    tenant_id: &str,
+    names: &[String],
+) -> Result<Vec<SecretRow>, Error> {
+    const SQL: &str = "SELECT * FROM secrets WHERE tenant_id = $1 AND name = ANY($2)";
+    let rows = sqlx::query_as::<_, SecretRow>(SQL)
+        .bind(tenant_id)
+        .bind(names)
+        .fetch_all(pool)
+        .await?;
+    Ok(rows)
+}
+
+pub async fn get_all(
+    pool: &Pool<Postgres>,
+    tenant_id: &str,
+    limit: i32,
+    offset: i32,
+) -> Result<Vec<SecretRow>, Error> {
+    const SQL: &str = "SELECT * FROM secrets WHERE tenant_id = $3 ORDER BY name LIMIT $1 OFFSET $2";
+    let rows = sqlx::query_as::<_, SecretRow>(SQL)
+        .bind(limit)
+        .bind(offset)
+        .bind(tenant_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(rows)
+}
+
+pub async fn delete(pool: &Pool<Postgres>, tenant_id: &str, name: &str) -> Result<u64, Error> {
+    const SQL: &str = "DELETE FROM secrets WHERE tenant_id = $1 AND name = $2";
+    let res = sqlx::query(SQL)
+        .bind(tenant_id)
+        .bind(name)
+        .execute(pool)
+        .await?;
+    Ok(res.rows_affected())
+}
+
+/// Secrets of every tenant sealed by another key than `key_id`
+pub async fn get_to_rewrap(pool: &Pool<Postgres>, key_id: &str) -> Result<Vec<SecretRow>, Error> {
+    const SQL: &str = "SELECT * FROM secrets WHERE envelope->>'key_id' <> $1";
+    let rows = sqlx::query_as::<_, SecretRow>(SQL)
+        .bind(key_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(rows)
+}
+
+/// Skipped when the secret was updated since it was read
+pub async fn update_envelope(
+    pool: &Pool<Postgres>,
+    row: &SecretRow,
+    envelope: &Envelope,
+) -> Result<u64, Error> {
+    const SQL: &str =
+        "UPDATE secrets SET envelope = $3 WHERE tenant_id = $1 AND name = $2 AND envelope = $4";
+    let res = sqlx::query(SQL)
+        .bind(&row.tenant_id)
+        .bind(&row.name)
+        .bind(Json(envelope))
+        .bind(Json(&row.envelope))
+        .execute(pool)
+        .await?;
+    Ok(res.rows_affected())
+}
@@ -0,0 +1,101 @@
+use crate::{
+    features::{Paging, PagingResult, apikeys::Tenant},
+    models::{AppState, Error},
+};
+use axum::{
+    Json, Router,
+    extract::{Path, Query, State},
+    http::{HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use problemdetails::Problem;
+use std::sync::Arc;
+
+use super::{
+    db,
+    secret::{SecretValue, is_valid_hosts, is_valid_secret_name, seal},
+};
+
+pub fn routes(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/secrets", get(get_all))
+        .route(
+            "/secrets/{name}",
+            get(get_by_name).put(upsert).delete(delete),
+        )
+        .with_state(state)
+}
+
+/// Creates or replaces a secret, pending jobs referencing it use the new value
+async fn upsert(
+    State(state): State<Arc<AppState>>,
+    Tenant(tenant_id): Tenant,
+    Path(name): Path<String>,
+    Json(secret): Json<SecretValue>,
+) -> Result<impl IntoResponse, Problem> {
+    if !is_valid_secret_name(&name) {
+        return Err(Error::InvalidParams("name").into());
+    }
+    // Resolved values end up in headers
+    if secret.value.is_empty() || HeaderValue::from_str(&secret.value).is_err() {
+        return Err(Error::InvalidParams("value").into());
+    }
+    if !is_valid_hosts(&secret.hosts) {
+        return Err(Error::InvalidParams("hosts").into());
+    }
+    let (value, envelope) = seal(&state.body_options.encryption, &secret.value)?;
+    let row = db::upsert(
+        &state.pool,
+        &tenant_id,
+        &name,
+        &value,
+        &envelope,
+        &secret.hosts,
+    )
+    .await?;
+    Ok(Json(row))
+}
+
+async fn get_all(
+    State(state): State<Arc<AppState>>,
+    Tenant(tenant_id): Tenant,
+    Query(pagination): Query<Paging>,
+) -> Result<impl IntoResponse, Problem> {
+    let result = db::get_all(
+        &state.pool,
+        &tenant_id,
+        pagination.limit.unwrap_or(10),
+        pagination.offset.unwrap_or(0),
+    )
+    .await?;
+    Ok(Json(PagingResult {
+        limit: pagination.limit.unwrap_or(10),
+        offset: pagination.offset.unwrap_or(0),
+        data: result,
+    }))
+}
+
+async fn get_by_name(
+    State(state): State<Arc<AppState>>,
+    Tenant(tenant_id): Tenant,
+    Path(name): Path<String>,
+) -> Result<Response, Problem> {
+    let row = db::get_by_name(&state.pool, &tenant_id, &name).await?;
+    match row {
+        None => Ok(StatusCode::NO_CONTENT.into_response()),
+        Some(o) => Ok(Json(o).into_response()),
+    }
+}
+
+async fn delete(
+    State(state): State<Arc<AppState>>,
+    Tenant(tenant_id): Tenant,
+    Path(name): Path<String>,
+) -> Result<Response, Problem> {
+    let rows = db::delete(&state.pool, &tenant_id, &name).await?;
+    match rows {
+        0 => Ok(StatusCode::NOT_FOUND.into_response()),
+        _ => Ok(StatusCode::NO_CONTENT.into_response()),
+    }
+}
@@ -0,0 +1,8 @@
+pub use http::routes;
+pub use resolve::resolve_headers;
+pub use secret::{SecretRow, rewrap_all};
+
+mod db;
+mod http;
+mod resolve;
+mod secret;
@@ -0,0 +1,116 @@
+use std::collections::HashMap;
+
+use crate::models::{AppState, Error};
+
+use super::{db, secret::is_valid_secret_name};
+
+const PREFIX: &str = "{{secret:";
+const SUFFIX: &str = "}}";
+
+/// Replaces every `{{secret:name}}` of `value` with `lookup(name)`, other text is kept
+fn replace(
+    value: &str,
+    mut lookup: impl FnMut(&str) -> Result<String, Error>,
+) -> Result<String, Error> {
+    let mut out = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find(PREFIX) {
+        let after = &rest[start + PREFIX.len()..];
+        let Some(end) = after.find(SUFFIX) else {
+            break;
+        };
+        let name = &after[..end];
+        out.push_str(&rest[..start]);
+        match is_valid_secret_name(name) {
+            true => out.push_str(&lookup(name)?),
+            false => out.push_str(&rest[start..start + PREFIX.len() + end + SUFFIX.len()]),
+        }
+        rest = &after[end + SUFFIX.len()..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Names referenced by a header value
+fn references(value: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    let _ = replace(value, |name| {
+        names.push(name.to_string());
+        Ok(String::new())
+    });
+    names
+}
+
+/// Replaces secret references in header values with the current values of the
+/// tenant's secrets, only at dispatch so stored jobs never hold them, and only
+/// when `host` is one of the secret's `hosts`
+pub async fn resolve_headers(
+    app_state: &AppState,
+    tenant_id: &str,
+    host: &str,
+    headers: &mut Option<HashMap<String, String>>,
+) -> Result<(), Error> {
+    let Some(headers) = headers.as_mut() else {
+        return Ok(());
+    };
+    let mut names: Vec<String> = headers.values().flat_map(|v| references(v)).collect();
+    if names.is_empty() {
+        return Ok(());
+    }
+    names.sort();
+    names.dedup();
+    let keys = &app_state.body_options.encryption;
+    let secrets = db::get_by_names(&app_state.pool, tenant_id, &names)
+        .await?
+        .into_iter()
+        .map(|row| match row.allows(host) {
+            true => Ok((row.name.clone(), row.open(keys)?)),
+            false => Err(Error::DestinationBlocked(format!(
+                "secret {} is not allowed for {}",
+                row.name, host
+            ))),
+        })
+        .collect::<Result<HashMap<String, String>, Error>>()?;
+    for value in headers.values_mut() {
+        *value = replace(value, |name| {
+            secrets
+                .get(name)
+                .cloned()
+                .ok_or_else(|| Error::SecretNotFound(name.to_string()))
+        })?;
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn replace_secret_references() -> anyhow::Result<()> {
+    // arrange
+    let secrets = HashMap::from([("partner_token".to_string(), "t0k3n".to_string())]);
+    let lookup = |name: &str| {
+        secrets
+            .get(name)
+            .cloned()
+            .ok_or_else(|| Error::SecretNotFound(name.to_string()))
+    };
+
+    // act & assert
+    assert_eq!(
+        "Bearer t0k3n",
+        replace("Bearer {{secret:partner_token}}", lookup)?
+    );
+    assert_eq!(
+        "t0k3n:t0k3n",
+        replace("{{secret:partner_token}}:{{secret:partner_token}}", lookup)?
+    );
+    assert_eq!(
+        "{{secret:a b}} {{secret:",
+        replace("{{secret:a b}} {{secret:", lookup)?
+    );
+    assert!(matches!(
+        replace("{{secret:missing}}", lookup),
+        Err(Error::SecretNotFound(name)) if name == "missing"
+    ));
+    assert_eq!(vec!["a", "b"], references("{{secret:a}}-{{secret:b}}"));
+    assert!(references("plain").is_empty());
+    Ok(())
+}
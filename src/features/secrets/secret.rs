@@ -0,0 +1,113 @@
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+
+use crate::models::{AppState, EncryptionKeys, Envelope, Error, HostRule};
+
+use super::db;
+
+/// Secret of a tenant, the value is sealed like job bodies and never returned
+#[derive(Debug, Clone, sqlx::FromRow, Serialize)]
+pub struct SecretRow {
+    #[serde(skip_serializing)]
+    pub tenant_id: String,
+    pub name: String,
+    #[serde(skip_serializing)]
+    pub value: Vec<u8>,
+    #[sqlx(json)]
+    #[serde(skip_serializing)]
+    pub envelope: Envelope,
+    /// Destinations the secret is sent to, hosts, `*.` domains, addresses and CIDRs
+    pub hosts: Vec<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SecretValue {
+    pub value: String,
+    pub hosts: Vec<String>,
+}
+
+pub fn is_valid_secret_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 64
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_' || b == b'.')
+}
+
+/// Sealed value and its envelope, secrets need `IRISQO_ENCRYPTION_KEYS`
+pub fn seal(keys: &EncryptionKeys, value: &str) -> Result<(Vec<u8>, Envelope), Error> {
+    let mut data = Bytes::copy_from_slice(value.as_bytes());
+    let envelope = keys
+        .seal(&mut data, &mut None)?
+        .ok_or(Error::Encryption("secrets need IRISQO_ENCRYPTION_KEYS"))?;
+    Ok((data.to_vec(), envelope))
+}
+
+/// At least one valid host rule
+pub fn is_valid_hosts(hosts: &[String]) -> bool {
+    !hosts.is_empty() && hosts.iter().all(|host| HostRule::parse(host).is_ok())
+}
+
+impl SecretRow {
+    /// Whether a job calling `host` may use the secret
+    pub fn allows(&self, host: &str) -> bool {
+        self.hosts
+            .iter()
+            .filter_map(|rule| HostRule::parse(rule).ok())
+            .any(|rule| rule.matches_host(host))
+    }
+
+    pub fn open(&self, keys: &EncryptionKeys) -> Result<String, Error> {
+        let mut value = Some(self.value.clone());
+        keys.open(&mut value, &mut None, &mut Some(self.envelope.clone()))?;
+        String::from_utf8(value.unwrap_or_default()).map_err(|_| Error::Encryption("secret"))
+    }
+}
+
+/// Rewraps the data keys of secrets sealed by an older key, see `services::reencrypt`
+pub async fn rewrap_all(app_state: &AppState, key_id: &str) -> Result<u64, Error> {
+    let keys = &app_state.body_options.encryption;
+    let mut count = 0;
+    for row in db::get_to_rewrap(&app_state.pool, key_id).await? {
+        if let Some(envelope) = keys.rewrap(&row.envelope)? {
+            count += db::update_envelope(&app_state.pool, &row, &envelope).await?;
+        }
+    }
+    Ok(count)
+}
+
+#[tokio::test]
+async fn secret_seal_open() -> anyhow::Result<()> {
+    // arrange
+    use base64::{Engine, engine::general_purpose::STANDARD};
+    let keys = EncryptionKeys::new(&format!("k1:{}", STANDARD.encode([7u8; 32])))?;
+
+    // act
+    let (value, envelope) = seal(&keys, "partner-token")?;
+    let row = SecretRow {
+        tenant_id: "default".into(),
+        name: "partner_token".into(),
+        value,
+        envelope,
+        hosts: vec!["api.partner.com".into(), "*.partner.net".into()],
+        created_at: chrono::Utc::now(),
+        updated_at: chrono::Utc::now(),
+    };
+
+    // assert
+    assert_eq!("partner-token", row.open(&keys)?);
+    assert!(!serde_json::to_string(&row)?.contains("partner-token"));
+    assert!(seal(&EncryptionKeys::default(), "x").is_err());
+    assert!(is_valid_secret_name("partner.token-1"));
+    assert!(!is_valid_secret_name("a b"));
+    assert!(row.allows("API.partner.com"));
+    assert!(row.allows("eu.partner.net"));
+    assert!(!row.allows("attacker.example"));
+    assert!(!row.allows("partner.net"));
+    assert!(!is_valid_hosts(&[]));
+    assert!(!is_valid_hosts(&["10.0.0.0/99".into()]));
+    Ok(())
+}
@@ -0,0 +1,123 @@
+use crate::models::{Error, JobEvent};
+
+use sqlx::{PgExecutor, Pool, Postgres, types::Json};
+
+use super::{SubscriptionCreate, SubscriptionRow};
+
+pub async fn create(
+    pool: &Pool<Postgres>,
+    tenant_id: &str,
+    subscription: SubscriptionCreate,
+    secret: &str,
+) -> Result<SubscriptionRow, Error> {
+    const SQL: &str = "
+    INSERT INTO subscriptions(subscription_id, url, events, filter, secret, tenant_id) VALUES ($1, $2, $3, $4, $5, $6) RETURNING *
+    ";
+    let subscription_id = ulid::Ulid::new().to_string();
+    let row = sqlx::query_as::<_, SubscriptionRow>(SQL)
+        .bind(subscription_id)
+        .bind(subscription.url)
+        .bind(subscription.events)
+        .bind(Json(subscription.filter))
+        .bind(secret)
+        .bind(tenant_id)
+        .fetch_one(pool)
+        .await?;
+    Ok(row)
+}
+
+pub async fn get_by_id(
+    pool: &Pool<Postgres>,
+    tenant_id: &str,
+    subscription_id: &str,
+) -> Result<Option<SubscriptionRow>, Error> {
+    const SQL: &str = "SELECT * FROM subscriptions WHERE subscription_id = $1 AND tenant_id = $2";
+    let row = sqlx::query_as::<_, SubscriptionRow>(SQL)
+        .bind(subscription_id)
+        .bind(tenant_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(row)
+}
+
+pub async fn get_all(
+    pool: &Pool<Postgres>,
+    tenant_id: &str,
+    limit: i32,
+    offset: i32,
+) -> Result<Vec<SubscriptionRow>, Error> {
+    const SQL: &str = "
+    SELECT * FROM subscriptions WHERE tenant_id = $3 ORDER BY subscription_id LIMIT $1 OFFSET $2;
+    ";
+    let res = sqlx::query_as::<_, SubscriptionRow>(SQL)
+        .bind(limit)
+        .bind(offset)
+        .bind(tenant_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(res)
+}
+
+pub async fn get_active_by_events(
+    conn: impl PgExecutor<'_>,
+    tenant_id: &str,
+    events: &[&str],
+) -> Result<Vec<SubscriptionRow>, Error> {
+    const SQL: &str =
+        "SELECT * FROM subscriptions WHERE NOT paused AND events && $1 AND tenant_id = $2";
+    let res = sqlx::query_as::<_, SubscriptionRow>(SQL)
+        .bind(events)
+        .bind(tenant_id)
+        .fetch_all(conn)
+        .await?;
+    Ok(res)
+}
+
+/// Oldest outbox events, locked until the transaction ends and skipped by other instances
+pub async fn lock_outbox(
+    conn: impl PgExecutor<'_>,
+    limit: i64,
+) -> Result<Vec<(i64, Json<JobEvent>)>, Error> {
+    const SQL: &str =
+        "SELECT seq, event FROM subscription_outbox ORDER BY seq LIMIT $1 FOR UPDATE SKIP LOCKED";
+    let res = sqlx::query_as(SQL).bind(limit).fetch_all(conn).await?;
+    Ok(res)
+}
+
+pub async fn delete_outbox(conn: impl PgExecutor<'_>, seqs: &[i64]) -> Result<u64, Error> {
+    const SQL: &str = "DELETE FROM subscription_outbox WHERE seq = ANY($1)";
+    let res = sqlx::query(SQL).bind(seqs).execute(conn).await?;
+    Ok(res.rows_affected())
+}
+
+pub async fn paused(
+    pool: &Pool<Postgres>,
+    tenant_id: &str,
+    subscription_id: &str,
+    paused: bool,
+) -> Result<u64, Error> {
+    const SQL: &str = "
+    UPDATE subscriptions SET paused = $2 WHERE subscription_id = $1 AND tenant_id = $3 RETURNING subscription_id
+    ";
+    let res = sqlx::query(SQL)
+        .bind(subscription_id)
+        .bind(paused)
+        .bind(tenant_id)
+        .execute(pool)
+        .await?;
+    Ok(res.rows_affected())
+}
+
+pub async fn delete(
+    pool: &Pool<Postgres>,
+    tenant_id: &str,
+    subscription_id: &str,
+) -> Result<u64, Error> {
+    const SQL: &str = "DELETE FROM subscriptions WHERE subscription_id = $1 AND tenant_id = $2";
+    let res = sqlx::query(SQL)
+        .bind(subscription_id)
+        .bind(tenant_id)
+        .execute(pool)
+        .await?;
+    Ok(res.rows_affected())
+}
@@ -0,0 +1,151 @@
+use std::collections::HashMap;
+
+use bytes::Bytes;
+use hyper::{Method, Uri, header};
+use sha2::{Digest, Sha256};
+use sqlx::{PgConnection, types::Json};
+use tracing::error;
+
+use crate::{
+    db,
+    models::{AppState, Error, HttpMeta, JobCreate, JobEvent, JobMeta, JobProtocol, JobRetry},
+};
+
+use super::SubscriptionRow;
+
+/// Retry policy of webhook deliveries
+pub const DELIVERY_RETRY: JobRetry = JobRetry::Fibonacci {
+    retry_count: 8,
+    retry_delay: 10,
+};
+
+/// Events drained from the outbox per transaction
+const OUTBOX_BATCH: i64 = 100;
+
+/// Subscription event names matching a `history` status
+fn subscription_events(event: &JobEvent) -> Vec<&str> {
+    let mut events = vec![event.status.as_str()];
+    if event.is_dead_lettered() {
+        events.push(JobEvent::DEAD_LETTERED);
+    }
+    events
+}
+
+/// Enqueues the delivery jobs of the oldest `subscription_outbox` events and
+/// removes them in the same transaction, returns the number of events drained
+pub async fn deliver_outbox(app_state: &AppState) -> Result<usize, Error> {
+    let mut tx = app_state.pool.begin().await?;
+    let events = super::db::lock_outbox(&mut *tx, OUTBOX_BATCH).await?;
+    for (_, Json(event)) in &events {
+        deliver(app_state, &mut tx, event).await?;
+    }
+    let seqs: Vec<i64> = events.iter().map(|(seq, _)| *seq).collect();
+    super::db::delete_outbox(&mut *tx, &seqs).await?;
+    tx.commit().await?;
+    Ok(events.len())
+}
+
+/// Enqueues a delivery job for every active subscription matching the event
+async fn deliver(
+    app_state: &AppState,
+    conn: &mut PgConnection,
+    event: &JobEvent,
+) -> Result<u64, Error> {
+    // Deliveries are jobs too, never deliver events about them
+    if event.subscription_id.is_some() {
+        return Ok(0);
+    }
+    let events = subscription_events(event);
+    let subscriptions =
+        super::db::get_active_by_events(&mut *conn, &event.tenant_id, &events).await?;
+    let mut delivered = 0;
+    for subscription in subscriptions.iter().filter(|s| s.filter.matches(event)) {
+        let job_id = event.id;
+        let subscription_id = &subscription.subscription_id;
+        // Never retried, a bad subscription must not block the outbox
+        let mut job = match delivery_job(subscription, event, app_state.worker_options.timeout) {
+            Ok(job) => job,
+            Err(err) => {
+                error!({ instance_id = app_state.instance_id, job_id, subscription_id }, "delivery_job error {:?}", err);
+                continue;
+            }
+        };
+        app_state.body_options.encode_job(&mut job).await?;
+        db::jobqueue::create_in(&mut *conn, job, &event.tenant_id, &app_state.instance_id).await?;
+        delivered += 1;
+    }
+    Ok(delivered)
+}
+
+fn delivery_job(
+    subscription: &SubscriptionRow,
+    event: &JobEvent,
+    timeout: u32,
+) -> Result<JobCreate, Error> {
+    let url = Uri::try_from(subscription.url.as_str()).map_err(|_| Error::InvalidUrl)?;
+    let body = serde_json::to_vec(event).map_err(|_| Error::InvalidParams("event"))?;
+    let headers = HashMap::from([
+        (
+            header::CONTENT_TYPE.to_string(),
+            "application/json".to_string(),
+        ),
+        ("irisqo-event".to_string(), event.status.clone()),
+        (
+            "irisqo-subscription-id".to_string(),
+            subscription.subscription_id.clone(),
+        ),
+    ]);
+    Ok(JobCreate {
+        meta: JobMeta {
+            protocol: JobProtocol::Http(HttpMeta {
+                method: Method::POST,
+                url,
+            }),
+            retry: DELIVERY_RETRY,
+            timeout,
+            subscription_id: Some(subscription.subscription_id.clone()),
+            ..Default::default()
+        },
+        headers: Some(headers),
+        body: Bytes::from(body),
+        body_ref: None,
+        body_codec: None,
+        external_id: Some(delivery_id(&subscription.subscription_id, event)),
+        ..Default::default()
+    })
+}
+
+/// Idempotency key of a delivery, fits `jobs.external_id`
+fn delivery_id(subscription_id: &str, event: &JobEvent) -> String {
+    let key = format!(
+        "{}:{}:{}:{}",
+        subscription_id, event.id, event.retry, event.status
+    );
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+#[tokio::test]
+async fn subscription_events_dead_lettered() -> anyhow::Result<()> {
+    // arrange
+    let mut event = JobEvent {
+        id: 42,
+        retry: 0,
+        instance_id: "host:01J".into(),
+        at: chrono::Utc::now(),
+        status: "failed".into(),
+        message: None,
+        schedule_id: None,
+        external_id: None,
+        subscription_id: None,
+        tenant_id: "default".into(),
+    };
+
+    // act & assert
+    assert_eq!(vec!["failed"], subscription_events(&event));
+    event.retry = 3;
+    assert_eq!(vec!["failed"], subscription_events(&event));
+    event.message = Some(JobEvent::DEAD_LETTERED.into());
+    assert_eq!(vec!["failed", "dead_lettered"], subscription_events(&event));
+    assert_eq!(64, delivery_id("01JSUBSCRIPTION", &event).len());
+    Ok(())
+}
@@ -0,0 +1,121 @@
+use crate::{
+    features::{Paging, PagingResult, apikeys::Tenant},
+    models::{AppState, Error},
+};
+use axum::{
+    Json, Router,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, put},
+};
+use hyper::Uri;
+use problemdetails::Problem;
+use std::sync::Arc;
+
+use super::{SUBSCRIPTION_EVENTS, SubscriptionCreate, SubscriptionCreated, signature};
+
+pub fn routes(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/subscriptions/{id}", get(get_by_id).delete(delete))
+        .route("/subscriptions/{id}/pause", put(pause))
+        .route("/subscriptions/{id}/resume", put(resume))
+        .route("/subscriptions", get(get_all).post(create))
+        .with_state(state)
+}
+
+async fn create(
+    State(state): State<Arc<AppState>>,
+    Tenant(tenant_id): Tenant,
+    Json(subscription): Json<SubscriptionCreate>,
+) -> Result<impl IntoResponse, Problem> {
+    let uri = Uri::try_from(subscription.url.as_str()).map_err(|_| Error::InvalidUrl)?;
+    if !matches!(uri.scheme_str(), Some("http") | Some("https")) {
+        return Err(Error::InvalidUrl.into());
+    }
+    state.destination_policy.check_uri(&uri)?;
+    if subscription.events.is_empty()
+        || subscription
+            .events
+            .iter()
+            .any(|e| !SUBSCRIPTION_EVENTS.contains(&e.as_str()))
+    {
+        return Err(Error::InvalidParams("events").into());
+    }
+    let secret = signature::new_secret();
+    let row = super::db::create(&state.pool, &tenant_id, subscription, &secret).await?;
+    Ok((
+        StatusCode::CREATED,
+        Json(SubscriptionCreated {
+            subscription: row,
+            secret,
+        }),
+    ))
+}
+
+async fn get_all(
+    State(state): State<Arc<AppState>>,
+    Tenant(tenant_id): Tenant,
+    Query(pagination): Query<Paging>,
+) -> Result<impl IntoResponse, Problem> {
+    let result = super::db::get_all(
+        &state.pool,
+        &tenant_id,
+        pagination.limit.unwrap_or(10),
+        pagination.offset.unwrap_or(0),
+    )
+    .await?;
+    Ok(Json(PagingResult {
+        limit: pagination.limit.unwrap_or(10),
+        offset: pagination.offset.unwrap_or(0),
+        data: result,
+    }))
+}
+
+async fn get_by_id(
+    State(state): State<Arc<AppState>>,
+    Tenant(tenant_id): Tenant,
+    Path(subscription_id): Path<String>,
+) -> Result<Response, Problem> {
+    let row = super::db::get_by_id(&state.pool, &tenant_id, &subscription_id).await?;
+    match row {
+        None => Ok(StatusCode::NO_CONTENT.into_response()),
+        Some(o) => Ok(Json(o).into_response()),
+    }
+}
+
+async fn pause(
+    State(state): State<Arc<AppState>>,
+    Tenant(tenant_id): Tenant,
+    Path(subscription_id): Path<String>,
+) -> Result<Response, Problem> {
+    let rows = super::db::paused(&state.pool, &tenant_id, &subscription_id, true).await?;
+    match rows {
+        0 => Ok(StatusCode::NOT_FOUND.into_response()),
+        _ => Ok(StatusCode::NO_CONTENT.into_response()),
+    }
+}
+
+async fn resume(
+    State(state): State<Arc<AppState>>,
+    Tenant(tenant_id): Tenant,
+    Path(subscription_id): Path<String>,
+) -> Result<Response, Problem> {
+    let rows = super::db::paused(&state.pool, &tenant_id, &subscription_id, false).await?;
+    match rows {
+        0 => Ok(StatusCode::NOT_FOUND.into_response()),
+        _ => Ok(StatusCode::NO_CONTENT.into_response()),
+    }
+}
+
+async fn delete(
+    State(state): State<Arc<AppState>>,
+    Tenant(tenant_id): Tenant,
+    Path(subscription_id): Path<String>,
+) -> Result<Response, Problem> {
+    let rows = super::db::delete(&state.pool, &tenant_id, &subscription_id).await?;
+    match rows {
+        0 => Ok(StatusCode::NOT_FOUND.into_response()),
+        _ => Ok(StatusCode::NO_CONTENT.into_response()),
+    }
+}
@@ -0,0 +1,23 @@
+pub use delivery::deliver_outbox;
+pub use http::routes;
+pub use subscription_row::{SubscriptionCreate, SubscriptionCreated, SubscriptionRow};
+
+pub(crate) use db::get_by_id;
+
+mod db;
+mod delivery;
+mod http;
+mod signature;
+mod subscription_row;
+
+/// Events a subscription can be registered for
+pub const SUBSCRIPTION_EVENTS: [&str; 8] = [
+    "scheduled",
+    "enqueued",
+    "assigned",
+    "retried",
+    "completed",
+    "failed",
+    "cancelled",
+    "dead_lettered",
+];
@@ -0,0 +1,3 @@
+pub fn new_secret() -> String {
+    format!("whsec_{}", uuid::Uuid::new_v4().simple())
+}
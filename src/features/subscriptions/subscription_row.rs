@@ -0,0 +1,32 @@
+use serde::{Deserialize, Serialize};
+
+use crate::features::events::EventsFilter;
+
+#[derive(Debug, Clone, sqlx::FromRow, Serialize)]
+pub struct SubscriptionRow {
+    pub subscription_id: String,
+    pub url: String,
+    pub events: Vec<String>,
+    #[sqlx(json)]
+    pub filter: EventsFilter,
+    #[serde(skip_serializing)]
+    pub secret: String,
+    pub paused: bool,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SubscriptionCreate {
+    pub url: String,
+    pub events: Vec<String>,
+    #[serde(default)]
+    pub filter: EventsFilter,
+}
+
+/// Returned once on create, the only time the signing secret is exposed
+#[derive(Debug, Clone, Serialize)]
+pub struct SubscriptionCreated {
+    #[serde(flatten)]
+    pub subscription: SubscriptionRow,
+    pub secret: String,
+}
@@ -0,0 +1,420 @@
+use std::collections::HashMap;
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use bytes::Bytes;
+use hyper::{
+    Method, Uri,
+    header::{HeaderName, HeaderValue},
+};
+use serde::Deserialize;
+
+use crate::{
+    features::schedules::JobSchedule,
+    models::{
+        Capture, Error, HttpMeta, IdConflict, JobCreate, JobMeta, JobProtocol, JobRetry,
+        ValidationError, WorkerOptions, parse_duration_secs,
+    },
+};
+
+/// Upper bound of items in `POST /api/v1/jobs/batch`
+pub const MAX_BATCH_ITEMS: usize = 10_000;
+
+/// JSON body of `POST /api/v1/jobs`, the counterpart of `/to/{url}` with `_` query params
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct JobEnvelope {
+    pub url: String,
+    pub method: Option<String>,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// String as is, any other JSON value serialized
+    pub body: Option<serde_json::Value>,
+    pub body_base64: Option<String>,
+    pub delay: Option<Seconds>,
+    pub at: Option<i64>,
+    pub jitter: Option<Seconds>,
+    /// Same syntax as `_retry`, e.g. `3|fibonacci|5`
+    pub retry: Option<String>,
+    /// Milliseconds
+    pub timeout: Option<u32>,
+    /// Interval in seconds or cron
+    pub schedule: Option<String>,
+    pub until: Option<i64>,
+    /// `created`, `epoch` or Unix Time
+    pub anchor: Option<String>,
+    pub external_id: Option<String>,
+    pub external_id_ttl: Option<Seconds>,
+    pub id_conflict: Option<String>,
+    /// none, headers, status, full or on_failure
+    pub capture: Option<String>,
+    /// Bytes
+    pub capture_max: Option<u32>,
+    /// TLS profile name
+    pub tls: Option<String>,
+}
+
+/// Seconds as a number or a duration string like `5m`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Seconds {
+    Number(u32),
+    Text(String),
+}
+
+impl Seconds {
+    fn secs(&self) -> Option<u32> {
+        match self {
+            Seconds::Number(n) => Some(*n),
+            Seconds::Text(s) => parse_duration_secs(s).ok(),
+        }
+    }
+}
+
+impl JobEnvelope {
+    /// Builds the same `JobCreate` as `/to/{url}`, collecting every validation error
+    pub fn into_job_create(
+        self,
+        options: &WorkerOptions,
+        trace_id: Option<String>,
+    ) -> Result<JobCreate, Vec<ValidationError>> {
+        let mut errors = Vec::new();
+        let now_secs = JobSchedule::now_secs();
+
+        let url = match Uri::try_from(self.url.as_str()) {
+            Ok(uri) if matches!(uri.scheme_str(), Some("http") | Some("https")) => Some(uri),
+            _ => {
+                errors.push(ValidationError::new(
+                    "url",
+                    "must be an absolute http(s) url",
+                ));
+                None
+            }
+        };
+        let method = match self.method.as_deref().map(str::to_uppercase) {
+            None => Some(Method::POST),
+            Some(m) => Method::from_bytes(m.as_bytes()).ok().or_else(|| {
+                errors.push(ValidationError::new("method", "invalid method"));
+                None
+            }),
+        };
+        for (name, value) in &self.headers {
+            if HeaderName::try_from(name.as_str()).is_err() {
+                errors.push(ValidationError::new(
+                    "headers",
+                    format!("invalid header name {}", name),
+                ));
+            } else if HeaderValue::try_from(value.as_str()).is_err() {
+                errors.push(ValidationError::new(
+                    "headers",
+                    format!("invalid value of header {}", name),
+                ));
+            }
+        }
+        let body = match (self.body, self.body_base64) {
+            (Some(_), Some(_)) => {
+                errors.push(ValidationError::new(
+                    "body",
+                    "body and body_base64 are mutually exclusive",
+                ));
+                Bytes::new()
+            }
+            (Some(serde_json::Value::String(s)), None) => Bytes::from(s),
+            (Some(value), None) => Bytes::from(value.to_string()),
+            (None, Some(b64)) => STANDARD.decode(b64).map(Bytes::from).unwrap_or_else(|_| {
+                errors.push(ValidationError::new("body_base64", "invalid base64"));
+                Bytes::new()
+            }),
+            (None, None) => Bytes::new(),
+        };
+
+        let delay = self.delay.as_ref().and_then(|d| {
+            d.secs().or_else(|| {
+                errors.push(ValidationError::new("delay", "invalid duration"));
+                None
+            })
+        });
+        if self.delay.is_some() && self.at.is_some() {
+            errors.push(ValidationError::new(
+                "at",
+                "delay and at are mutually exclusive",
+            ));
+        }
+        if self.at.is_some_and(|t| t <= now_secs) && self.schedule.is_none() {
+            errors.push(ValidationError::new("at", "must be in the future"));
+        }
+        let at = self.at.or_else(|| delay.map(|d| now_secs + i64::from(d)));
+        let delay = delay.or_else(|| self.at.and_then(|t| (t - now_secs).try_into().ok()));
+        let jitter = self.jitter.as_ref().and_then(|j| {
+            j.secs().or_else(|| {
+                errors.push(ValidationError::new("jitter", "invalid duration"));
+                None
+            })
+        });
+        let retry = match self.retry.as_deref().map(str::parse::<JobRetry>) {
+            None => JobRetry::None,
+            Some(Ok(retry)) => retry,
+            Some(Err(_)) => {
+                errors.push(ValidationError::new("retry", "invalid retry"));
+                JobRetry::None
+            }
+        };
+        let schedule = self.schedule.as_deref().and_then(|s| {
+            s.parse::<JobSchedule>().ok().or_else(|| {
+                errors.push(ValidationError::new(
+                    "schedule",
+                    "must be an interval in seconds or cron",
+                ));
+                None
+            })
+        });
+        let anchor = match self.anchor.as_deref() {
+            None | Some("epoch") => None,
+            Some("created") => Some(now_secs),
+            Some(s) => s.parse::<i64>().ok().or_else(|| {
+                errors.push(ValidationError::new(
+                    "anchor",
+                    "must be created, epoch or Unix Time",
+                ));
+                None
+            }),
+        };
+        if self
+            .external_id
+            .as_ref()
+            .is_some_and(|id| id.is_empty() || id.len() > 64)
+        {
+            errors.push(ValidationError::new(
+                "external_id",
+                "must be 1 to 64 characters",
+            ));
+        }
+        let external_id_ttl = match &self.external_id_ttl {
+            None => options.id_ttl,
+            Some(ttl) => ttl.secs().or_else(|| {
+                errors.push(ValidationError::new("external_id_ttl", "invalid duration"));
+                None
+            }),
+        };
+        let id_conflict = match self.id_conflict.as_deref().map(str::parse::<IdConflict>) {
+            None => IdConflict::default(),
+            Some(Ok(id_conflict)) => id_conflict,
+            Some(Err(_)) => {
+                errors.push(ValidationError::new(
+                    "id_conflict",
+                    "must be return_existing, reject_409 or replace_if_pending",
+                ));
+                IdConflict::default()
+            }
+        };
+        let capture = self.capture.as_deref().and_then(|c| {
+            c.parse::<Capture>().ok().or_else(|| {
+                errors.push(ValidationError::new(
+                    "capture",
+                    "must be none, headers, status, full or on_failure",
+                ));
+                None
+            })
+        });
+
+        let (Some(url), Some(method), true) = (url, method, errors.is_empty()) else {
+            return Err(errors);
+        };
+        Ok(JobCreate {
+            id: None,
+            meta: JobMeta {
+                protocol: JobProtocol::Http(HttpMeta { method, url }),
+                retry,
+                delay,
+                jitter: jitter.filter(|&j| j > 0),
+                timeout: self.timeout.unwrap_or(options.timeout),
+                trace_id,
+                subscription_id: None,
+                capture,
+                capture_max: self.capture_max,
+                tls: self.tls,
+            },
+            headers: Some(self.headers),
+            body,
+            body_ref: None,
+            body_codec: None,
+            envelope: None,
+            at,
+            schedule,
+            until: self.until,
+            anchor,
+            external_id: self.external_id,
+            external_id_ttl,
+            id_conflict,
+        })
+    }
+}
+
+/// Splits a batch body, a JSON array or one envelope per line for `application/x-ndjson`,
+/// items failing to deserialize are reported with their own errors.
+/// NDJSON lines are parsed as soon as their chunk arrives, arrays once complete.
+#[derive(Debug, Default)]
+pub struct BatchParser {
+    ndjson: bool,
+    /// The incomplete last line, or the whole array
+    pending: Vec<u8>,
+    items: Vec<Result<JobEnvelope, Vec<ValidationError>>>,
+}
+
+impl BatchParser {
+    pub fn new(ndjson: bool) -> Self {
+        Self {
+            ndjson,
+            ..Default::default()
+        }
+    }
+
+    pub fn push(&mut self, chunk: &[u8]) -> Result<(), Error> {
+        self.pending.extend_from_slice(chunk);
+        if !self.ndjson {
+            return Ok(());
+        }
+        if let Some(end) = self.pending.iter().rposition(|&b| b == b'\n') {
+            let rest = self.pending.split_off(end + 1);
+            let lines = std::mem::replace(&mut self.pending, rest);
+            for line in lines.split(|&b| b == b'\n') {
+                self.push_line(line)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<Vec<Result<JobEnvelope, Vec<ValidationError>>>, Error> {
+        let pending = std::mem::take(&mut self.pending);
+        if self.ndjson {
+            self.push_line(&pending)?;
+        } else {
+            let values =
+                serde_json::from_slice::<Vec<serde_json::Value>>(&pending).map_err(|err| {
+                    Error::Validation(vec![ValidationError::new("$", err.to_string())])
+                })?;
+            for value in values {
+                self.push_item(serde_json::from_value(value))?;
+            }
+        }
+        Ok(self.items)
+    }
+
+    fn push_line(&mut self, line: &[u8]) -> Result<(), Error> {
+        if line.trim_ascii().is_empty() {
+            return Ok(());
+        }
+        self.push_item(serde_json::from_slice(line))
+    }
+
+    fn push_item(&mut self, res: serde_json::Result<JobEnvelope>) -> Result<(), Error> {
+        if self.items.len() == MAX_BATCH_ITEMS {
+            return Err(Error::Validation(vec![ValidationError::new(
+                "$",
+                format!("at most {} items", MAX_BATCH_ITEMS),
+            )]));
+        }
+        self.items
+            .push(res.map_err(|err| vec![ValidationError::new("$", err.to_string())]));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+fn worker_options() -> WorkerOptions {
+    WorkerOptions {
+        workers_count: 1,
+        poll_interval: std::time::Duration::from_millis(1000),
+        prefetch: 1,
+        timeout: 3000,
+        id_ttl: None,
+        capture: Default::default(),
+        capture_max: None,
+    }
+}
+
+#[tokio::test]
+async fn job_envelope_into_job_create_ok() -> anyhow::Result<()> {
+    // arrange
+    let envelope: JobEnvelope = serde_json::from_value(serde_json::json!({
+        "url": "https://example.com/hook?_retry=keep",
+        "method": "put",
+        "headers": { "host": "example.org", "content-type": "application/json" },
+        "body": { "name": "irisqo" },
+        "delay": "5m",
+        "retry": "3|fibonacci|5",
+        "external_id": "order-42"
+    }))?;
+
+    // act
+    let job = envelope.into_job_create(&worker_options(), None);
+
+    // assert
+    let job = job.unwrap();
+    let JobProtocol::Http(meta) = job.meta.protocol else {
+        panic!("http protocol expected");
+    };
+    assert_eq!(Method::PUT, meta.method);
+    assert_eq!(
+        "/hook?_retry=keep",
+        meta.url.path_and_query().unwrap().as_str()
+    );
+    assert_eq!(Some(300), job.meta.delay);
+    assert_eq!(3000, job.meta.timeout);
+    assert_eq!(Bytes::from(r#"{"name":"irisqo"}"#), job.body);
+    assert_eq!(
+        Some("example.org"),
+        job.headers
+            .as_ref()
+            .unwrap()
+            .get("host")
+            .map(String::as_str)
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn job_envelope_into_job_create_errors() -> anyhow::Result<()> {
+    // arrange
+    let envelope: JobEnvelope = serde_json::from_value(serde_json::json!({
+        "url": "ftp://example.com",
+        "body": "text",
+        "body_base64": "dGV4dA==",
+        "retry": "many",
+        "schedule": "every day"
+    }))?;
+
+    // act
+    let errors = envelope
+        .into_job_create(&worker_options(), None)
+        .unwrap_err();
+
+    // assert
+    let fields: Vec<&str> = errors.iter().map(|e| e.field).collect();
+    assert_eq!(vec!["url", "body", "retry", "schedule"], fields);
+    Ok(())
+}
+
+#[tokio::test]
+async fn batch_parser_ndjson_per_item_errors() -> anyhow::Result<()> {
+    // arrange
+    let body = b"{\"url\": \"https://example.com/a\"}\n\n{\"url\": 1}\n{\"url\": \"https://example.com/b\", \"delay\": 5}\n";
+    let parse = |ndjson: bool, chunk_size: usize| {
+        let mut parser = BatchParser::new(ndjson);
+        for chunk in body.chunks(chunk_size) {
+            parser.push(chunk)?;
+        }
+        parser.finish()
+    };
+
+    // act
+    let items = parse(true, 7)?;
+
+    // assert
+    assert_eq!(3, items.len());
+    assert!(items[0].is_ok());
+    assert_eq!("$", items[1].as_ref().unwrap_err()[0].field);
+    assert!(items[2].is_ok());
+    assert_eq!(3, parse(true, body.len())?.len());
+    assert!(parse(false, 7).is_err());
+    Ok(())
+}
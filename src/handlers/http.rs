@@ -1,13 +1,16 @@
 use crate::{
     db,
-    features::schedules::JobSchedule,
-    models::{AppState, Error, HttpMeta, JobCreate, JobMeta, JobRetry},
+    features::{apikeys::Tenant, quotas, results, schedules::JobSchedule},
+    models::{
+        AppState, Capture, Error, HttpMeta, IdConflict, JobCreate, JobCreateRow, JobMeta, JobRetry,
+        header_options, parse_duration_secs,
+    },
     otel,
 };
 use axum::{
     Router,
     body::Bytes,
-    extract::{Path, RawQuery, State},
+    extract::{DefaultBodyLimit, Path, RawQuery, State, rejection::BytesRejection},
     http::{HeaderMap, StatusCode, header},
     response::IntoResponse,
     routing::get,
@@ -15,11 +18,16 @@ use axum::{
 use hyper::{Method, Uri};
 use problemdetails::Problem;
 
-use std::{collections::HashMap, sync::Arc};
+use std::{borrow::Cow, sync::Arc};
 #[allow(unused_imports)]
 use tracing::{debug, error, info, warn};
 use url::{Url, form_urlencoded};
 
+/// Default `_sync=true` wait in seconds
+const SYNC_WAIT_SECS: u32 = 30;
+/// Default `_dedup=content` window in seconds when no `_id_ttl` is set
+const DEDUP_WINDOW_SECS: u32 = 10 * 60;
+
 pub fn routes(state: Arc<AppState>) -> Router {
     Router::new()
         .route(
@@ -28,79 +36,171 @@ pub fn routes(state: Arc<AppState>) -> Router {
                 .post(job_create)
                 .put(job_create)
                 .delete(job_create)
-                .patch(job_create),
+                .patch(job_create)
+                .layer(DefaultBodyLimit::max(state.body_options.max_request)),
         )
         .with_state(state)
 }
 
 async fn job_create(
     State(state): State<Arc<AppState>>,
+    Tenant(tenant_id): Tenant,
     method: Method,
     Path(url): Path<String>,
     RawQuery(query): RawQuery,
     headers: HeaderMap,
-    body: Bytes,
+    body: Result<Bytes, BytesRejection>,
 ) -> Result<impl IntoResponse, Problem> {
+    let body = body.map_err(|err| match err.status() {
+        StatusCode::PAYLOAD_TOO_LARGE => Error::BodyTooLarge(state.body_options.max_request),
+        _ => Error::InvalidParams("body"),
+    })?;
     let mut delay: Option<u32> = None;
+    let mut jitter: Option<u32> = None;
     let mut at: Option<i64> = None;
     let mut timeout: u32 = state.worker_options.timeout;
     let mut retry: JobRetry = JobRetry::None;
     let mut schedule: Option<JobSchedule> = None;
     let mut until: Option<i64> = None;
+    let mut anchor: Option<i64> = None;
     let mut external_id: Option<String> = None;
+    let mut sync: Option<u32> = None;
+    let mut external_id_ttl: Option<u32> = state.worker_options.id_ttl;
+    let mut id_conflict = IdConflict::default();
+    let mut dedup_content = false;
+    let mut capture: Option<Capture> = None;
+    let mut capture_max: Option<u32> = None;
+    let mut tls: Option<String> = None;
+    let mut dedup_headers: Vec<String> = vec![header::CONTENT_TYPE.to_string()];
 
-    // Parse and truncate Query String
+    // Parse and truncate Query String, `Irisqo-*` header options come first so params win
     let mut parsed_url = Url::parse(&url).map_err(|_| Error::InvalidUrl)?;
-    if let Some(qs) = query {
-        let params = form_urlencoded::parse(qs.as_bytes());
-        let now_secs = JobSchedule::now_secs();
-        for (key, value) in params {
-            if key == "_delay" {
-                delay = value.parse::<u32>().ok();
-                at = delay.map(|t| now_secs + i64::from(t));
-                continue;
-            }
-            if key == "_delay_until" {
-                at = value.parse::<i64>().ok().filter(|&t| t > now_secs);
-                delay = at.and_then(|t| (t - now_secs).try_into().ok());
-                continue;
-            }
-            if key == "_timeout" {
-                timeout = value.parse::<u32>().unwrap_or(state.worker_options.timeout);
-                continue;
-            }
-            if key == "_retry" {
-                retry = value.parse()?;
-                continue;
-            }
-            if key == "_interval" {
-                let job_schedule = value
-                    .parse()
-                    .map_err(|_| Error::InvalidParams("interval"))?;
-                schedule = Some(job_schedule);
-                continue;
-            }
-            if key == "_cron" {
-                let job_schedule = value.parse().map_err(|_| Error::InvalidParams("cron"))?;
-                schedule = Some(job_schedule);
-                continue;
-            }
-            if key == "_until" {
-                until = value.parse::<i64>().ok();
-                continue;
-            }
-            if key == "_id" && !value.is_empty() && value.len() < 65 {
-                external_id = Some(value.to_string());
-                continue;
-            }
-            if value.is_empty() {
-                parsed_url.query_pairs_mut().append_key_only(key.as_ref());
-                continue;
-            }
-            parsed_url
-                .query_pairs_mut()
-                .append_pair(key.as_ref(), value.as_ref());
+    let header_params = header_options(&headers);
+    let header_count = header_params.len();
+    let params = header_params
+        .into_iter()
+        .map(|(key, value)| (Cow::Owned(key), Cow::Owned(value)))
+        .chain(
+            query
+                .iter()
+                .flat_map(|qs| form_urlencoded::parse(qs.as_bytes())),
+        );
+    let now_secs = JobSchedule::now_secs();
+    for (idx, (key, value)) in params.enumerate() {
+        if key == "_delay" {
+            delay = value.parse::<u32>().ok();
+            at = delay.map(|t| now_secs + i64::from(t));
+            continue;
+        }
+        if key == "_delay_until" {
+            at = value.parse::<i64>().ok().filter(|&t| t > now_secs);
+            delay = at.and_then(|t| (t - now_secs).try_into().ok());
+            continue;
+        }
+        if key == "_jitter" {
+            jitter = Some(parse_duration_secs(&value)?).filter(|&j| j > 0);
+            continue;
+        }
+        if key == "_timeout" {
+            timeout = value.parse::<u32>().unwrap_or(state.worker_options.timeout);
+            continue;
+        }
+        if key == "_retry" {
+            retry = value.parse()?;
+            continue;
+        }
+        if key == "_interval" {
+            let job_schedule = value
+                .parse()
+                .map_err(|_| Error::InvalidParams("interval"))?;
+            schedule = Some(job_schedule);
+            continue;
+        }
+        if key == "_cron" {
+            let job_schedule = value.parse().map_err(|_| Error::InvalidParams("cron"))?;
+            schedule = Some(job_schedule);
+            continue;
+        }
+        if key == "_anchor" {
+            anchor = match value.as_ref() {
+                "epoch" => None,
+                "created" => Some(now_secs),
+                _ => Some(
+                    value
+                        .parse::<i64>()
+                        .map_err(|_| Error::InvalidParams("anchor"))?,
+                ),
+            };
+            continue;
+        }
+        if key == "_until" {
+            until = value.parse::<i64>().ok();
+            continue;
+        }
+        if key == "_sync" {
+            sync = match value.as_ref() {
+                "false" | "0" => None,
+                "true" | "" => Some(SYNC_WAIT_SECS),
+                _ => Some(parse_duration_secs(&value).map_err(|_| Error::InvalidParams("sync"))?),
+            };
+            continue;
+        }
+        if key == "_id_ttl" {
+            external_id_ttl =
+                Some(parse_duration_secs(&value).map_err(|_| Error::InvalidParams("id_ttl"))?);
+            continue;
+        }
+        if key == "_id_conflict" {
+            id_conflict = value.parse()?;
+            continue;
+        }
+        if key == "_capture" {
+            capture = Some(value.parse()?);
+            continue;
+        }
+        if key == "_capture_max" {
+            capture_max = Some(
+                value
+                    .parse::<u32>()
+                    .map_err(|_| Error::InvalidParams("capture_max"))?,
+            );
+            continue;
         }
+        if key == "_tls" {
+            tls = Some(value.to_string()).filter(|t| !t.is_empty());
+            continue;
+        }
+        if key == "_dedup" {
+            dedup_content = match value.as_ref() {
+                "content" => true,
+                "none" => false,
+                _ => return Err(Error::InvalidParams("dedup").into()),
+            };
+            continue;
+        }
+        if key == "_dedup_headers" {
+            dedup_headers = value
+                .split(',')
+                .map(|h| h.trim().to_string())
+                .filter(|h| !h.is_empty())
+                .collect();
+            continue;
+        }
+        if key == "_id" && !value.is_empty() && value.len() < 65 {
+            external_id = Some(value.to_string());
+            continue;
+        }
+        // Unknown header options are not query params of the destination
+        if idx < header_count {
+            continue;
+        }
+        if value.is_empty() {
+            parsed_url.query_pairs_mut().append_key_only(key.as_ref());
+            continue;
+        }
+        parsed_url
+            .query_pairs_mut()
+            .append_pair(key.as_ref(), value.as_ref());
     }
     let uri = Uri::try_from(parsed_url.as_str()).map_err(|_| Error::InvalidUrl)?;
     let scheme = uri.scheme_str();
@@ -112,49 +212,86 @@ async fn job_create(
     };
 
     // Parse Headers
-    let mut header_hashmap = HashMap::new();
-    for (k, v) in headers {
-        if let Some(key) = k {
-            if key == header::HOST {
-                continue;
-            }
-            if let Ok(val) = v.to_str() {
-                header_hashmap.insert(key.to_string(), val.to_owned());
-            }
-        }
-    }
+    let header_hashmap = state.forward_options.forward(&headers);
     // OpenTelemetry TraceId
     let trace_id = otel::current_trace_id();
     // Build
-    let job_create = JobCreate {
+    let mut job_create = JobCreate {
+        id: None,
         meta: JobMeta {
             protocol,
             retry,
             delay,
+            jitter,
             timeout,
             trace_id,
+            subscription_id: None,
+            capture,
+            capture_max,
+            tls,
         },
         headers: Some(header_hashmap),
         body,
+        body_ref: None,
+        body_codec: None,
+        envelope: None,
         at,
         schedule,
         until,
+        anchor,
         external_id,
+        external_id_ttl,
+        id_conflict,
     };
 
+    // Content-based dedup, an explicit `_id` wins
+    if dedup_content && job_create.external_id.is_none() {
+        job_create.external_id = job_create.content_key(&dedup_headers);
+        if job_create.external_id_ttl.is_none() {
+            job_create.external_id_ttl = Some(DEDUP_WINDOW_SECS);
+        }
+    }
+    state.destination_policy.check_job(&job_create)?;
+    state.clients.check_job(&job_create)?;
+    let quota = quotas::effective(&state, &tenant_id).await?;
+    quota.check_job(&job_create)?;
+    quotas::check_jobs(
+        &state,
+        &tenant_id,
+        &quota,
+        std::slice::from_ref(&job_create),
+    )
+    .await?;
+    state.body_options.encode_job(&mut job_create).await?;
     debug!("{:?}", serde_json::to_string(&job_create.meta));
-    let job = db::jobqueue::create(&state.pool, job_create, &state.instance_id).await?;
+    let job = db::jobqueue::create(&state.pool, job_create, &tenant_id, &state.instance_id).await?;
+    let headers = created_headers(&job);
+    if let Some(secs) = sync {
+        let job_result =
+            results::wait_result(&state, &tenant_id, job.id, results::wait_timeout(secs)).await?;
+        let Some(job_result) = job_result else {
+            return Ok((StatusCode::ACCEPTED, headers).into_response());
+        };
+        let mut response = job_result.into_response();
+        response.headers_mut().extend(headers);
+        return Ok(response);
+    }
+    Ok((StatusCode::CREATED, headers).into_response())
+}
+
+/// `Location`, `job-id`, `schedule-id` and `external-id` headers of a created job
+pub(crate) fn created_headers(job: &JobCreateRow) -> HeaderMap {
     let mut headers = HeaderMap::new();
     headers.insert(
         header::LOCATION,
         format!("/api/v1/jobs/{}", job.id).parse().unwrap(),
     );
     headers.insert("job-id", job.id.into());
-    if let Some(schedule_id) = job.schedule_id {
+    if let Some(schedule_id) = &job.schedule_id {
         headers.insert("schedule-id", schedule_id.parse().unwrap());
     }
-    if let Some(external_id) = job.external_id {
+    if let Some(external_id) = &job.external_id {
         headers.insert("external-id", external_id.parse().unwrap());
     }
-    Ok((StatusCode::CREATED, headers))
+    headers
 }
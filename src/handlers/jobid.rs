@@ -0,0 +1,43 @@
+use std::{collections::HashMap, sync::Arc};
+
+use axum::{
+    extract::{FromRequestParts, Path},
+    http::request::Parts,
+};
+use problemdetails::Problem;
+
+use crate::{
+    db,
+    features::apikeys::Tenant,
+    models::{AppState, Error},
+};
+
+/// Job id from either an `{id}` or an `{external_id}` path parameter,
+/// external ids are resolved in the tenant of the request
+#[derive(Debug, Clone, Copy)]
+pub struct JobId(pub i64);
+
+impl FromRequestParts<Arc<AppState>> for JobId {
+    type Rejection = Problem;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        let Path(params) = Path::<HashMap<String, String>>::from_request_parts(parts, state)
+            .await
+            .map_err(|_| Error::InvalidParams("id"))?;
+        if let Some(id) = params.get("id") {
+            let id = id.parse().map_err(|_| Error::InvalidParams("id"))?;
+            return Ok(JobId(id));
+        }
+        let external_id = params
+            .get("external_id")
+            .ok_or(Error::InvalidParams("id"))?;
+        let Ok(Tenant(tenant_id)) = Tenant::from_request_parts(parts, state).await;
+        let job = db::jobqueue::get_id_by_external_id(&state.pool, &tenant_id, external_id)
+            .await?
+            .ok_or_else(|| Error::ExternalIdNotFound(external_id.clone()))?;
+        Ok(JobId(job.id))
+    }
+}
@@ -1,35 +1,388 @@
-use crate::{db, models::AppState};
+use crate::{
+    db,
+    features::{
+        CursorPaging, CursorPagingResult,
+        apikeys::{Tenant, Unredacted},
+        quotas,
+        results::{self, CancelOutcome},
+        schedules::{self, JobSchedule},
+    },
+    handlers::{
+        JobId,
+        envelope::{BatchParser, JobEnvelope},
+        http::created_headers,
+    },
+    models::{AppState, BodyCodec, Error, JobCreate, JobCreateRow, JobSearch, ValidationError},
+    otel,
+};
 use axum::{
     Json, Router,
-    extract::{Path, State},
-    http::StatusCode,
+    body::Body,
+    extract::{DefaultBodyLimit, Query, State, rejection::JsonRejection},
+    http::{HeaderMap, StatusCode, header},
     response::{IntoResponse, Response},
-    routing::get,
+    routing::{get, post, put},
 };
+use bytes::Bytes;
+use futures::StreamExt;
 use problemdetails::Problem;
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
+/// Request body limit of `/jobs/batch`
+const BATCH_BODY_LIMIT: usize = 64 * 1024 * 1024;
+
+const JOB_STATES: [&str; 6] = [
+    "scheduled",
+    "enqueued",
+    "assigned",
+    "completed",
+    "failed",
+    "cancelled",
+];
+
 pub fn routes(state: Arc<AppState>) -> Router {
     Router::new()
+        .route(
+            "/jobs",
+            get(get_all)
+                .post(create)
+                .layer(DefaultBodyLimit::max(envelope_body_limit(&state))),
+        )
+        .route("/jobs/batch", post(create_batch))
         .route("/jobs/{id}", get(get_by_id).delete(delete_by_id))
+        .route(
+            "/jobs/by-external-id/{external_id}",
+            get(get_by_id).delete(delete_by_id),
+        )
+        .route("/jobs/{id}/cancel", put(cancel_by_id))
+        .route(
+            "/jobs/by-external-id/{external_id}/cancel",
+            put(cancel_by_id),
+        )
+        .route("/jobs/{id}/replay", post(replay_by_id))
+        .route(
+            "/jobs/by-external-id/{external_id}/replay",
+            post(replay_by_id),
+        )
         .with_state(state)
 }
 
+/// Room for JSON and base64 around a body of `--max-body`
+fn envelope_body_limit(state: &AppState) -> usize {
+    state.body_options.max_request.saturating_mul(2)
+}
+
+async fn create(
+    State(state): State<Arc<AppState>>,
+    Tenant(tenant_id): Tenant,
+    payload: Result<Json<JobEnvelope>, JsonRejection>,
+) -> Result<impl IntoResponse, Problem> {
+    let Json(envelope) = payload.map_err(|err| match err.status() {
+        StatusCode::PAYLOAD_TOO_LARGE => Error::BodyTooLarge(envelope_body_limit(&state)),
+        _ => Error::Validation(vec![ValidationError::new("$", err.body_text())]),
+    })?;
+    let mut job_create = envelope
+        .into_job_create(&state.worker_options, otel::current_trace_id())
+        .map_err(Error::Validation)?;
+    if let Some(headers) = job_create.headers.as_mut() {
+        state.forward_options.retain(headers);
+    }
+    if job_create.body.len() > state.body_options.max_request {
+        return Err(Error::BodyTooLarge(state.body_options.max_request).into());
+    }
+    state.destination_policy.check_job(&job_create)?;
+    state.clients.check_job(&job_create)?;
+    let quota = quotas::effective(&state, &tenant_id).await?;
+    quota.check_job(&job_create)?;
+    quotas::check_jobs(
+        &state,
+        &tenant_id,
+        &quota,
+        std::slice::from_ref(&job_create),
+    )
+    .await?;
+    state.body_options.encode_job(&mut job_create).await?;
+    let job = db::jobqueue::create(&state.pool, job_create, &tenant_id, &state.instance_id).await?;
+    Ok((StatusCode::CREATED, created_headers(&job), Json(job)))
+}
+
+#[derive(Debug, Deserialize)]
+struct BatchOptions {
+    /// Nothing is created when any item is invalid
+    #[serde(default)]
+    atomic: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct BatchResult {
+    created: usize,
+    failed: usize,
+    items: Vec<BatchItem>,
+}
+
+#[derive(Debug, Serialize)]
+struct BatchItem {
+    index: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    job: Option<JobCreateRow>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    errors: Vec<ValidationError>,
+}
+
+impl BatchResult {
+    fn new(items: Vec<BatchItem>) -> Self {
+        let failed = items.iter().filter(|item| !item.errors.is_empty()).count();
+        Self {
+            created: items.len() - failed,
+            failed,
+            items,
+        }
+    }
+}
+
+async fn create_batch(
+    State(state): State<Arc<AppState>>,
+    Tenant(tenant_id): Tenant,
+    Query(options): Query<BatchOptions>,
+    headers: HeaderMap,
+    body: Body,
+) -> Result<Response, Problem> {
+    let ndjson = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/x-ndjson"));
+    let mut parser = BatchParser::new(ndjson);
+    let mut stream = body.into_data_stream();
+    let mut received = 0;
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|_| Error::InvalidParams("body"))?;
+        received += chunk.len();
+        if received > BATCH_BODY_LIMIT {
+            return Err(Error::BodyTooLarge(BATCH_BODY_LIMIT).into());
+        }
+        parser.push(&chunk)?;
+    }
+    let trace_id = otel::current_trace_id();
+    let max_body = state.body_options.max_request;
+    let quota = quotas::effective(&state, &tenant_id).await?;
+    let mut items: Vec<BatchItem> = Vec::new();
+    let mut jobs = Vec::new();
+    for (index, envelope) in parser.finish()?.into_iter().enumerate() {
+        let job_create = envelope
+            .and_then(|e| e.into_job_create(&state.worker_options, trace_id.clone()))
+            .and_then(|job| match job.body.len() > max_body {
+                true => Err(vec![ValidationError::new(
+                    "body",
+                    format!("larger than {} bytes", max_body),
+                )]),
+                false => Ok(job),
+            })
+            .and_then(|job| match state.destination_policy.check_job(&job) {
+                Err(err) => Err(vec![ValidationError::new("url", err.to_string())]),
+                Ok(()) => Ok(job),
+            })
+            .and_then(|job| match state.clients.check_job(&job) {
+                Err(err) => Err(vec![ValidationError::new("tls", err.to_string())]),
+                Ok(()) => Ok(job),
+            })
+            .and_then(|job| match quota.check_job(&job) {
+                Err(err) => Err(vec![ValidationError::new("quota", err.to_string())]),
+                Ok(()) => Ok(job),
+            });
+        match job_create {
+            Ok(mut job) => {
+                if let Some(headers) = job.headers.as_mut() {
+                    state.forward_options.retain(headers);
+                }
+                state.body_options.encode_job(&mut job).await?;
+                jobs.push(job)
+            }
+            Err(errors) => items.push(BatchItem {
+                index,
+                job: None,
+                errors,
+            }),
+        }
+    }
+    if options.atomic && !items.is_empty() {
+        return Ok((StatusCode::BAD_REQUEST, Json(BatchResult::new(items))).into_response());
+    }
+    quotas::check_jobs(&state, &tenant_id, &quota, &jobs).await?;
+
+    let created = db::jobqueue::create_batch(
+        &state.pool,
+        jobs,
+        &tenant_id,
+        &state.instance_id,
+        options.atomic,
+    )
+    .await?;
+    // Invalid items keep their index, created ones fill the gaps in order
+    let mut failed = std::mem::take(&mut items).into_iter().peekable();
+    let mut created = created.into_iter();
+    let mut index = 0;
+    loop {
+        let item = match failed.next_if(|item| item.index == index) {
+            Some(item) => item,
+            None => match created.next() {
+                Some(Ok(job)) => BatchItem {
+                    index,
+                    job: Some(job),
+                    errors: Vec::new(),
+                },
+                Some(Err(err)) => {
+                    let field = match err {
+                        Error::ExternalIdConflict(_) => "external_id",
+                        Error::InvalidParams(field) => field,
+                        _ => "$",
+                    };
+                    BatchItem {
+                        index,
+                        job: None,
+                        errors: vec![ValidationError::new(field, err.to_string())],
+                    }
+                }
+                None => break,
+            },
+        };
+        items.push(item);
+        index += 1;
+    }
+    items.extend(failed);
+    Ok(Json(BatchResult::new(items)).into_response())
+}
+
+async fn get_all(
+    State(state): State<Arc<AppState>>,
+    Tenant(tenant_id): Tenant,
+    Unredacted(unredacted): Unredacted,
+    Query(paging): Query<CursorPaging>,
+    Query(search): Query<JobSearch>,
+) -> Result<impl IntoResponse, Problem> {
+    if search
+        .state
+        .as_deref()
+        .is_some_and(|s| !JOB_STATES.contains(&s))
+    {
+        return Err(Error::InvalidParams("state").into());
+    }
+    let limit = paging.limit.unwrap_or(100).clamp(1, 1000);
+    let mut data =
+        db::jobqueue::search(&state.pool, &tenant_id, &search, paging.cursor, limit).await?;
+    let encryption = &state.body_options.encryption;
+    for row in data.iter_mut() {
+        let job = &mut row.job;
+        encryption.open(&mut job.body, &mut job.headers, &mut job.envelope)?;
+        BodyCodec::decode(&mut job.body, &mut job.body_codec)?;
+        if !unredacted {
+            state.redaction.redact(&mut job.headers);
+        }
+    }
+    let next_cursor = (data.len() == limit as usize)
+        .then(|| data.last().map(|row| row.job.id))
+        .flatten();
+    Ok(Json(CursorPagingResult {
+        limit,
+        next_cursor,
+        data,
+    }))
+}
+
+async fn cancel_by_id(
+    State(state): State<Arc<AppState>>,
+    Tenant(tenant_id): Tenant,
+    JobId(id): JobId,
+) -> Result<StatusCode, Problem> {
+    let cancelled = results::cancel(&state.pool, &tenant_id, id, &state.instance_id).await?;
+    let CancelOutcome::Cancelled { schedule_id, at } = cancelled else {
+        return match cancelled {
+            CancelOutcome::NotFound => Err(Error::JobNotFound(id).into()),
+            _ => Ok(StatusCode::CONFLICT),
+        };
+    };
+    // The schedule goes on with the occurrence after the cancelled one
+    if let Some(schedule_id) = schedule_id {
+        let after = at.unwrap_or_default().max(JobSchedule::now_secs());
+        if let Some(next_at) =
+            schedules::next_at(&state.pool, &tenant_id, &schedule_id, after).await?
+        {
+            db::jobqueue::clone_schedule_at(&state.pool, id, next_at, &state.instance_id).await?;
+        }
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn replay_by_id(
+    State(state): State<Arc<AppState>>,
+    Tenant(tenant_id): Tenant,
+    JobId(id): JobId,
+) -> Result<impl IntoResponse, Problem> {
+    let mut stored = db::jobqueue::get_by_id(&state.pool, &tenant_id, id)
+        .await?
+        .ok_or(Error::JobNotFound(id))?;
+    // The copy is checked like a new job, the stored row is copied as is
+    state
+        .body_options
+        .decode(
+            &mut stored.body,
+            &mut stored.headers,
+            stored.body_ref.as_deref(),
+            &mut stored.body_codec,
+            &mut stored.envelope,
+        )
+        .await?;
+    let job_create = JobCreate {
+        meta: stored.meta,
+        headers: stored.headers,
+        body: stored.body.map_or(Bytes::new(), Bytes::from),
+        ..Default::default()
+    };
+    state.destination_policy.check_job(&job_create)?;
+    state.clients.check_job(&job_create)?;
+    let quota = quotas::effective(&state, &tenant_id).await?;
+    quota.check_job(&job_create)?;
+    quotas::check_jobs(
+        &state,
+        &tenant_id,
+        &quota,
+        std::slice::from_ref(&job_create),
+    )
+    .await?;
+    let job = db::jobqueue::replay(&state.pool, &tenant_id, id, &state.instance_id)
+        .await?
+        .ok_or(Error::JobNotFound(id))?;
+    Ok((StatusCode::CREATED, created_headers(&job), Json(job)))
+}
+
 async fn delete_by_id(
     State(state): State<Arc<AppState>>,
-    Path(id): Path<i64>,
+    Tenant(tenant_id): Tenant,
+    JobId(id): JobId,
 ) -> Result<StatusCode, Problem> {
-    let _ = db::jobqueue::delete(&state.pool, id).await?;
+    let _ = db::jobqueue::delete(&state.pool, &tenant_id, id).await?;
     Ok(StatusCode::NO_CONTENT)
 }
 
 async fn get_by_id(
     State(state): State<Arc<AppState>>,
-    Path(id): Path<i64>,
+    Tenant(tenant_id): Tenant,
+    Unredacted(unredacted): Unredacted,
+    JobId(id): JobId,
 ) -> Result<Response, Problem> {
-    let job = db::jobqueue::get_by_id(&state.pool, id).await?;
+    let job = db::jobqueue::get_by_id(&state.pool, &tenant_id, id).await?;
     match job {
         None => Ok(StatusCode::NO_CONTENT.into_response()),
-        Some(o) => Ok(Json(o).into_response()),
+        Some(mut o) => {
+            state
+                .body_options
+                .encryption
+                .open(&mut o.body, &mut o.headers, &mut o.envelope)?;
+            BodyCodec::decode(&mut o.body, &mut o.body_codec)?;
+            if !unredacted {
+                state.redaction.redact(&mut o.headers);
+            }
+            Ok(Json(o).into_response())
+        }
     }
 }
@@ -1,2 +1,7 @@
 pub mod http;
 pub mod jobs;
+
+pub use jobid::JobId;
+
+mod envelope;
+mod jobid;
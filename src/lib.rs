@@ -0,0 +1,2 @@
+//! Helpers for services receiving requests from irisqo, the server itself is the `irisqo` binary
+pub mod signing;
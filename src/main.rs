@@ -1,10 +1,10 @@
 use std::net::SocketAddr;
 use std::sync::Arc;
 
-use axum::Router;
+use axum::{Router, middleware};
 use models::AppState;
 use opentelemetry::trace::TracerProvider as _;
-use opentelemetry_sdk::trace::SdkTracerProvider;
+use opentelemetry_sdk::{metrics::SdkMeterProvider, trace::SdkTracerProvider};
 use opentelemetry_stdout as stdout;
 use tokio::net::TcpListener;
 use tokio::signal;
@@ -30,6 +30,11 @@ async fn main() {
 
     let tracer = provider.tracer("irisqo");
     opentelemetry::global::set_tracer_provider(provider);
+    // Metrics are printed to stdout as well
+    let meter_provider = SdkMeterProvider::builder()
+        .with_periodic_exporter(stdout::MetricExporter::default())
+        .build();
+    opentelemetry::global::set_meter_provider(meter_provider);
 
     tracing_subscriber::registry()
         .with(
@@ -41,10 +46,26 @@ async fn main() {
         .init();
 
     let state = AppState::new().await;
+    if state.reencrypt {
+        match services::reencrypt::run(&state).await {
+            Ok(count) => eprintln!("->> re-encrypted {} rows", count),
+            Err(err) => {
+                eprintln!("->> re-encrypt failed: {}", err);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    features::apikeys::bootstrap(&state)
+        .await
+        .expect("Unable to create the admin key");
     tokio::join!(
         start_http_server(&state),
         services::start_scheduler_service(&state),
         services::start_channel_worker_service(&state),
+        services::start_listener_service(&state),
+        services::start_subscription_service(&state),
+        services::start_blob_gc_service(&state),
     );
 
     eprintln!("->> SHUTDOWN")
@@ -58,9 +79,21 @@ async fn start_http_server(state: &Arc<AppState>) {
         .merge(features::echo::routes(Arc::clone(state)))
         .nest("/api/v1", handlers::jobs::routes(Arc::clone(state)))
         .nest("/api/v1", features::history::routes(Arc::clone(state)))
+        .nest("/api/v1", features::events::routes(Arc::clone(state)))
         .nest("/api/v1", features::results::routes(Arc::clone(state)))
         .nest("/api/v1", features::schedules::routes(Arc::clone(state)))
+        .nest(
+            "/api/v1",
+            features::subscriptions::routes(Arc::clone(state)),
+        )
         .nest("/api/v1", features::instances::routes(Arc::clone(state)))
+        .nest("/api/v1", features::apikeys::routes(Arc::clone(state)))
+        .nest("/api/v1", features::quotas::routes(Arc::clone(state)))
+        .nest("/api/v1", features::secrets::routes(Arc::clone(state)))
+        .layer(middleware::from_fn_with_state(
+            Arc::clone(state),
+            features::apikeys::auth,
+        ))
         .layer(
             TraceLayer::new_for_http()
                 .make_span_with(otel::make_span_from_request)
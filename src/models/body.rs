@@ -0,0 +1,325 @@
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
+
+use bytes::Bytes;
+use sha2::{Digest, Sha256};
+use tokio::fs;
+
+use super::{BodyCodec, EncryptionKeys, Envelope, Error, JobCreate, codec::MIN_COMPRESS_BYTES};
+use crate::otel;
+
+/// Default `--max-body` in bytes
+pub const DEFAULT_MAX_REQUEST_BODY: usize = 2 * 1024 * 1024;
+/// Default `--max-response-body` in bytes
+pub const DEFAULT_MAX_RESPONSE_BODY: usize = 10 * 1024 * 1024;
+/// Default `--blob-threshold` in bytes
+pub const DEFAULT_BLOB_THRESHOLD: usize = 64 * 1024;
+
+/// Size limits, compression, encryption and offloading of stored bodies
+#[derive(Debug)]
+pub struct BodyOptions {
+    /// Larger request bodies are rejected with `413`
+    pub max_request: usize,
+    /// Larger response bodies are truncated
+    pub max_response: usize,
+    /// Larger bodies go to `blob_store` when configured
+    pub blob_threshold: usize,
+    pub blob_store: Option<BlobStore>,
+    pub codec: Option<BodyCodec>,
+    /// Seals bodies and headers when keys are configured
+    pub encryption: EncryptionKeys,
+}
+
+/// How a body is stored, the result of `BodyOptions::encode`
+#[derive(Debug, Clone, Default)]
+pub struct BodyEncoding {
+    pub body_ref: Option<String>,
+    pub codec: Option<BodyCodec>,
+    /// Set when the body and headers are sealed
+    pub envelope: Option<Envelope>,
+}
+
+impl Default for BodyOptions {
+    fn default() -> Self {
+        Self {
+            max_request: DEFAULT_MAX_REQUEST_BODY,
+            max_response: DEFAULT_MAX_RESPONSE_BODY,
+            blob_threshold: DEFAULT_BLOB_THRESHOLD,
+            blob_store: None,
+            codec: None,
+            encryption: EncryptionKeys::default(),
+        }
+    }
+}
+
+impl BodyOptions {
+    /// Compresses, seals and then offloads a body, sealed headers move into the envelope.
+    /// `kind` is `request` or `response` in metrics
+    pub async fn encode(
+        &self,
+        body: &mut Bytes,
+        headers: &mut Option<HashMap<String, String>>,
+        kind: &'static str,
+    ) -> Result<BodyEncoding, Error> {
+        let mut encoding = self.encode_inline(body, headers, kind)?;
+        encoding.body_ref = self.offload(body).await?;
+        Ok(encoding)
+    }
+
+    /// Compresses and seals a body kept in the row
+    pub fn encode_inline(
+        &self,
+        body: &mut Bytes,
+        headers: &mut Option<HashMap<String, String>>,
+        kind: &'static str,
+    ) -> Result<BodyEncoding, Error> {
+        let codec = self.compress(body, kind)?;
+        let envelope = self.encryption.seal(body, headers)?;
+        Ok(BodyEncoding {
+            body_ref: None,
+            codec,
+            envelope,
+        })
+    }
+
+    pub async fn encode_job(&self, job: &mut JobCreate) -> Result<(), Error> {
+        let encoding = self
+            .encode(&mut job.body, &mut job.headers, "request")
+            .await?;
+        job.body_ref = encoding.body_ref;
+        job.body_codec = encoding.codec;
+        job.envelope = encoding.envelope;
+        Ok(())
+    }
+
+    /// Loads an offloaded body, opens it and the headers and decompresses the body,
+    /// `codec` and `envelope` are cleared
+    pub async fn decode(
+        &self,
+        body: &mut Option<Vec<u8>>,
+        headers: &mut Option<HashMap<String, String>>,
+        body_ref: Option<&str>,
+        codec: &mut Option<String>,
+        envelope: &mut Option<Envelope>,
+    ) -> Result<(), Error> {
+        self.load(body, body_ref).await?;
+        self.encryption.open(body, headers, envelope)?;
+        BodyCodec::decode(body, codec)
+    }
+
+    /// Seals a body and headers stored in plaintext, an offloaded body is stored
+    /// again sealed under a new `body_ref`, the plaintext blob is left to the
+    /// blob collection once no row references it
+    pub async fn seal_stored(
+        &self,
+        body: &mut Option<Vec<u8>>,
+        headers: &mut Option<HashMap<String, String>>,
+        body_ref: &mut Option<String>,
+    ) -> Result<Option<Envelope>, Error> {
+        self.load(body, body_ref.as_deref()).await?;
+        let mut data = body.take().map_or(Bytes::new(), Bytes::from);
+        let envelope = self.encryption.seal(&mut data, headers)?;
+        if let (Some(_), Some(store)) = (body_ref.as_ref(), &self.blob_store) {
+            *body_ref = Some(store.put(&data).await?);
+        } else if !data.is_empty() {
+            *body = Some(data.to_vec());
+        }
+        Ok(envelope)
+    }
+
+    /// Keeps the compressed body only when it is smaller
+    fn compress(&self, body: &mut Bytes, kind: &'static str) -> Result<Option<BodyCodec>, Error> {
+        let Some(codec) = self.codec else {
+            return Ok(None);
+        };
+        if body.len() < MIN_COMPRESS_BYTES {
+            return Ok(None);
+        }
+        let compressed = codec.compress(body)?;
+        if compressed.len() >= body.len() {
+            otel::record_body_compression(codec, kind, body.len(), body.len());
+            return Ok(None);
+        }
+        otel::record_body_compression(codec, kind, body.len(), compressed.len());
+        *body = Bytes::from(compressed);
+        Ok(Some(codec))
+    }
+
+    /// Moves a body over the threshold into the blob store, returns its `body_ref`
+    async fn offload(&self, body: &mut Bytes) -> Result<Option<String>, Error> {
+        let Some(store) = &self.blob_store else {
+            return Ok(None);
+        };
+        if body.len() <= self.blob_threshold {
+            return Ok(None);
+        }
+        let key = store.put(body).await?;
+        *body = Bytes::new();
+        Ok(Some(key))
+    }
+
+    /// Loads an offloaded body back in place
+    async fn load(&self, body: &mut Option<Vec<u8>>, body_ref: Option<&str>) -> Result<(), Error> {
+        let (Some(key), None) = (body_ref, body.as_ref()) else {
+            return Ok(());
+        };
+        let store = self
+            .blob_store
+            .as_ref()
+            .ok_or(Error::InvalidParams("blob store"))?;
+        *body = Some(store.get(key).await?);
+        Ok(())
+    }
+}
+
+/// Backend of offloaded bodies, referenced by `body_ref` in `jobs` and `processed`.
+/// Keys are the sha256 of the content, so equal bodies are stored once.
+#[derive(Debug)]
+pub enum BlobStore {
+    /// Directory shared by all instances, e.g. a mounted volume
+    Fs(PathBuf),
+}
+
+impl BlobStore {
+    pub async fn put(&self, body: &[u8]) -> Result<String, Error> {
+        let key = hex::encode(Sha256::digest(body));
+        match self {
+            BlobStore::Fs(dir) => {
+                let path = Self::fs_path(dir, &key);
+                if fs::try_exists(&path).await? {
+                    // A fresh modification time keeps the blob out of the next collection
+                    let file = fs::OpenOptions::new().append(true).open(&path).await?;
+                    file.into_std().await.set_modified(SystemTime::now())?;
+                    return Ok(key);
+                }
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent).await?;
+                }
+                // Write and rename so readers never see a partial blob
+                let tmp = path.with_extension(ulid::Ulid::new().to_string());
+                fs::write(&tmp, body).await?;
+                fs::rename(&tmp, &path).await?;
+            }
+        }
+        Ok(key)
+    }
+
+    pub async fn get(&self, key: &str) -> Result<Vec<u8>, Error> {
+        if key.is_empty() || !key.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(Error::InvalidParams("body_ref"));
+        }
+        match self {
+            BlobStore::Fs(dir) => Ok(fs::read(Self::fs_path(dir, key)).await?),
+        }
+    }
+
+    /// Keys of blobs not modified for `age`, the candidates of a collection
+    pub async fn keys_older_than(&self, age: Duration) -> Result<Vec<String>, Error> {
+        let mut keys = Vec::new();
+        match self {
+            BlobStore::Fs(dir) => {
+                let Ok(mut prefixes) = fs::read_dir(dir).await else {
+                    return Ok(keys);
+                };
+                while let Some(prefix) = prefixes.next_entry().await? {
+                    if !prefix.file_type().await?.is_dir() {
+                        continue;
+                    }
+                    let mut entries = fs::read_dir(prefix.path()).await?;
+                    while let Some(entry) = entries.next_entry().await? {
+                        let Ok(key) = entry.file_name().into_string() else {
+                            continue;
+                        };
+                        // Skips the temporary files of writes in progress
+                        if !key.bytes().all(|b| b.is_ascii_hexdigit()) {
+                            continue;
+                        }
+                        let modified = entry.metadata().await?.modified()?;
+                        if modified.elapsed().unwrap_or_default() >= age {
+                            keys.push(key);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(keys)
+    }
+
+    /// Deletes a blob unless it was written or reused within `age` since it was listed
+    pub async fn delete_older_than(&self, key: &str, age: Duration) -> Result<bool, Error> {
+        if key.is_empty() || !key.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(Error::InvalidParams("body_ref"));
+        }
+        match self {
+            BlobStore::Fs(dir) => {
+                let path = Self::fs_path(dir, key);
+                let modified = match fs::metadata(&path).await {
+                    Ok(metadata) => metadata.modified()?,
+                    Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+                    Err(err) => return Err(err.into()),
+                };
+                if modified.elapsed().unwrap_or_default() < age {
+                    return Ok(false);
+                }
+                match fs::remove_file(path).await {
+                    Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+                    res => Ok(res.is_ok()),
+                }
+            }
+        }
+    }
+
+    fn fs_path(dir: &std::path::Path, key: &str) -> PathBuf {
+        dir.join(&key[..2]).join(key)
+    }
+}
+
+#[tokio::test]
+async fn body_options_offload_and_load() -> anyhow::Result<()> {
+    // arrange
+    let dir = std::env::temp_dir().join(format!("irisqo-blobs-{}", ulid::Ulid::new()));
+    let options = BodyOptions {
+        blob_threshold: 4,
+        blob_store: Some(BlobStore::Fs(dir.clone())),
+        ..Default::default()
+    };
+    let mut small = Bytes::from_static(b"tiny");
+    let mut large = Bytes::from_static(b"large body");
+
+    // act
+    let small_ref = options.offload(&mut small).await?;
+    let large_ref = options.offload(&mut large).await?;
+    let mut loaded = None;
+    options.load(&mut loaded, large_ref.as_deref()).await?;
+
+    // assert
+    assert_eq!(None, small_ref);
+    assert_eq!(Bytes::from_static(b"tiny"), small);
+    assert!(large.is_empty());
+    assert_eq!(Some(b"large body".to_vec()), loaded);
+    assert!(BlobStore::Fs(dir.clone()).get("../etc").await.is_err());
+    let store = options.blob_store.as_ref().unwrap();
+    assert_eq!(
+        large_ref.clone().into_iter().collect::<Vec<_>>(),
+        store.keys_older_than(Duration::ZERO).await?
+    );
+    assert!(
+        store
+            .keys_older_than(Duration::from_secs(60))
+            .await?
+            .is_empty()
+    );
+    let key = large_ref.as_deref().unwrap();
+    assert!(
+        !store
+            .delete_older_than(key, Duration::from_secs(60))
+            .await?
+    );
+    assert!(store.delete_older_than(key, Duration::ZERO).await?);
+    assert!(store.keys_older_than(Duration::ZERO).await?.is_empty());
+    fs::remove_dir_all(dir).await?;
+    Ok(())
+}
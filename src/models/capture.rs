@@ -0,0 +1,70 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use super::Error;
+
+/// What of a destination response is stored in `processed`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Capture {
+    /// Nothing for successes, the status of failures
+    None,
+    Headers,
+    Status,
+    #[default]
+    Full,
+    /// Everything for failures, the status of successes
+    OnFailure,
+}
+
+impl Capture {
+    /// Policy for a response, the status of a failure is always kept as it decides retries
+    pub fn resolve(self, failed: bool) -> Capture {
+        match (self, failed) {
+            (Capture::OnFailure, true) => Capture::Full,
+            (Capture::OnFailure, false) => Capture::Status,
+            (Capture::None, true) => Capture::Status,
+            (capture, _) => capture,
+        }
+    }
+
+    pub fn headers(self) -> bool {
+        matches!(self, Capture::Headers | Capture::Full)
+    }
+
+    pub fn body(self) -> bool {
+        self == Capture::Full
+    }
+}
+
+impl FromStr for Capture {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Capture::None),
+            "headers" => Ok(Capture::Headers),
+            "status" => Ok(Capture::Status),
+            "full" => Ok(Capture::Full),
+            "on_failure" => Ok(Capture::OnFailure),
+            _ => Err(Error::InvalidParams("capture")),
+        }
+    }
+}
+
+#[tokio::test]
+async fn capture_resolve() -> anyhow::Result<()> {
+    // act & assert
+    assert_eq!(
+        Capture::Full,
+        "on_failure".parse::<Capture>()?.resolve(true)
+    );
+    assert_eq!(Capture::Status, Capture::OnFailure.resolve(false));
+    assert_eq!(Capture::Status, Capture::None.resolve(true));
+    assert_eq!(Capture::None, Capture::None.resolve(false));
+    assert!(Capture::Headers.resolve(true).headers());
+    assert!(!Capture::Headers.resolve(true).body());
+    assert!("all".parse::<Capture>().is_err());
+    Ok(())
+}
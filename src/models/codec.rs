@@ -0,0 +1,94 @@
+use std::{
+    fmt::Display,
+    io::{Read, Write},
+    str::FromStr,
+};
+
+use super::Error;
+
+/// Smaller bodies are stored as is
+pub const MIN_COMPRESS_BYTES: usize = 256;
+
+/// Compression of stored bodies, recorded in `body_codec` of `jobs` and `processed`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BodyCodec {
+    Zstd,
+    Gzip,
+}
+
+impl BodyCodec {
+    pub fn compress(&self, data: &[u8]) -> Result<Vec<u8>, Error> {
+        match self {
+            BodyCodec::Zstd => Ok(zstd::encode_all(data, zstd::DEFAULT_COMPRESSION_LEVEL)?),
+            BodyCodec::Gzip => {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(data)?;
+                Ok(encoder.finish()?)
+            }
+        }
+    }
+
+    pub fn decompress(&self, data: &[u8]) -> Result<Vec<u8>, Error> {
+        match self {
+            BodyCodec::Zstd => Ok(zstd::decode_all(data)?),
+            BodyCodec::Gzip => {
+                let mut decoded = Vec::new();
+                flate2::read::GzDecoder::new(data).read_to_end(&mut decoded)?;
+                Ok(decoded)
+            }
+        }
+    }
+
+    /// Decompresses a stored body in place, `codec` is cleared
+    pub fn decode(body: &mut Option<Vec<u8>>, codec: &mut Option<String>) -> Result<(), Error> {
+        let Some(name) = codec.take() else {
+            return Ok(());
+        };
+        if let Some(data) = body.as_deref() {
+            *body = Some(name.parse::<BodyCodec>()?.decompress(data)?);
+        }
+        Ok(())
+    }
+}
+
+impl Display for BodyCodec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BodyCodec::Zstd => write!(f, "zstd"),
+            BodyCodec::Gzip => write!(f, "gzip"),
+        }
+    }
+}
+
+impl FromStr for BodyCodec {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "zstd" => Ok(BodyCodec::Zstd),
+            "gzip" => Ok(BodyCodec::Gzip),
+            _ => Err(Error::InvalidParams("codec")),
+        }
+    }
+}
+
+#[tokio::test]
+async fn body_codec_roundtrip() -> anyhow::Result<()> {
+    // arrange
+    let data = br#"{"name": "irisqo", "tags": ["a", "b", "c"]}"#.repeat(64);
+
+    for codec in [BodyCodec::Zstd, BodyCodec::Gzip] {
+        // act
+        let compressed = codec.compress(&data)?;
+        let mut body = Some(compressed.clone());
+        let mut name = Some(codec.to_string());
+        BodyCodec::decode(&mut body, &mut name)?;
+
+        // assert
+        assert!(compressed.len() < data.len() / 10);
+        assert_eq!(Some(data.clone()), body);
+        assert_eq!(None, name);
+    }
+    Ok(())
+}
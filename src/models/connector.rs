@@ -0,0 +1,240 @@
+use std::{
+    future::Future,
+    io,
+    net::SocketAddr,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll, ready},
+};
+
+use hyper::Uri;
+use hyper::rt::{Read, ReadBufCursor, Write};
+use hyper_tls::MaybeHttpsStream;
+use hyper_util::{
+    client::legacy::connect::{
+        Connected, Connection, HttpConnector,
+        dns::{GaiResolver, Name},
+    },
+    rt::TokioIo,
+};
+use tokio::net::TcpStream;
+use tower::Service;
+
+use super::{DestinationPolicy, proxy::ProxyConnector, util::BoxError};
+
+type BoxFuture<T, E> = Pin<Box<dyn Future<Output = Result<T, E>> + Send>>;
+
+/// Resolves with `getaddrinfo` and drops the addresses the policy blocks, a
+/// name that passed on enqueue can not be rebound to an internal address
+#[derive(Clone)]
+pub struct DestinationResolver {
+    inner: GaiResolver,
+    policy: Arc<DestinationPolicy>,
+}
+
+impl DestinationResolver {
+    pub fn new(policy: Arc<DestinationPolicy>) -> Self {
+        Self {
+            inner: GaiResolver::new(),
+            policy,
+        }
+    }
+}
+
+impl Service<Name> for DestinationResolver {
+    type Response = std::vec::IntoIter<SocketAddr>;
+    type Error = io::Error;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, name: Name) -> Self::Future {
+        let policy = Arc::clone(&self.policy);
+        let resolving = self.inner.call(name.clone());
+        Box::pin(async move {
+            let mut blocked = None;
+            let addrs: Vec<SocketAddr> = resolving
+                .await?
+                .filter(
+                    |addr| match policy.check_ip(Some(name.as_str()), addr.ip()) {
+                        Ok(()) => true,
+                        Err(reason) => {
+                            blocked.get_or_insert(reason);
+                            false
+                        }
+                    },
+                )
+                .collect();
+            match (addrs.is_empty(), blocked) {
+                (true, Some(reason)) => {
+                    Err(io::Error::new(io::ErrorKind::PermissionDenied, reason))
+                }
+                _ => Ok(addrs.into_iter()),
+            }
+        })
+    }
+}
+
+/// `HttpConnector` checking ports and address literals of every connect, jobs
+/// enqueued before a policy change included
+#[derive(Clone)]
+pub struct DestinationConnector {
+    inner: HttpConnector<DestinationResolver>,
+    policy: Arc<DestinationPolicy>,
+}
+
+impl DestinationConnector {
+    pub fn new(policy: Arc<DestinationPolicy>) -> Self {
+        let mut inner =
+            HttpConnector::new_with_resolver(DestinationResolver::new(Arc::clone(&policy)));
+        // TLS is added by `TlsProfileConnector`
+        inner.enforce_http(false);
+        Self { inner, policy }
+    }
+}
+
+impl Service<Uri> for DestinationConnector {
+    type Response = <HttpConnector<DestinationResolver> as Service<Uri>>::Response;
+    type Error = BoxError;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        if let Err(err) = self.policy.check_uri(&uri) {
+            return Box::pin(async move { Err(err.into()) });
+        }
+        let connecting = self.inner.call(uri);
+        Box::pin(async move { connecting.await.map_err(Into::into) })
+    }
+}
+
+/// Connection to a destination, or to the proxy of an `http` destination
+/// which then gets absolute-form requests
+pub struct DestinationStream {
+    io: TokioIo<TcpStream>,
+    proxied: bool,
+}
+
+impl DestinationStream {
+    pub fn new(io: TokioIo<TcpStream>, proxied: bool) -> Self {
+        Self { io, proxied }
+    }
+}
+
+impl Read for DestinationStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: ReadBufCursor<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_read(cx, buf)
+    }
+}
+
+impl Write for DestinationStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.io).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_shutdown(cx)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.io.is_write_vectored()
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.io).poll_write_vectored(cx, bufs)
+    }
+}
+
+impl Connection for DestinationStream {
+    fn connected(&self) -> Connected {
+        self.io.connected().proxy(self.proxied)
+    }
+}
+
+/// `HttpsConnector` of a TLS profile, the handshake sends and verifies `sni`
+/// instead of the url host when set. Destinations with a proxy are reached
+/// through it, see `ProxyConnector`.
+#[derive(Clone)]
+pub struct TlsProfileConnector {
+    http: DestinationConnector,
+    proxy: ProxyConnector,
+    tls: tokio_native_tls::TlsConnector,
+    sni: Option<String>,
+}
+
+impl TlsProfileConnector {
+    pub fn new(
+        http: DestinationConnector,
+        proxy: ProxyConnector,
+        tls: native_tls::TlsConnector,
+        sni: Option<String>,
+    ) -> Self {
+        Self {
+            http,
+            proxy,
+            tls: tls.into(),
+            sni,
+        }
+    }
+}
+
+impl Service<Uri> for TlsProfileConnector {
+    type Response = MaybeHttpsStream<DestinationStream>;
+    type Error = BoxError;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        ready!(self.http.poll_ready(cx))?;
+        self.proxy.poll_ready(cx)
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let https = uri.scheme_str() == Some("https");
+        let server_name = self.sni.clone().unwrap_or_else(|| {
+            uri.host()
+                .unwrap_or_default()
+                .trim_matches(['[', ']'])
+                .to_string()
+        });
+        let tls = self.tls.clone();
+        let connecting: BoxFuture<_, BoxError> = match self.proxy.connect(&uri) {
+            Some(connecting) => match self.http.policy.check_uri(&uri) {
+                Ok(()) => Box::pin(connecting),
+                Err(err) => Box::pin(async move { Err(err.into()) }),
+            },
+            None => {
+                let connecting = self.http.call(uri);
+                Box::pin(async move { Ok(DestinationStream::new(connecting.await?, false)) })
+            }
+        };
+        Box::pin(async move {
+            let tcp = connecting.await?;
+            if !https {
+                return Ok(MaybeHttpsStream::Http(tcp));
+            }
+            let stream = tls.connect(&server_name, TokioIo::new(tcp)).await?;
+            Ok(MaybeHttpsStream::Https(TokioIo::new(stream)))
+        })
+    }
+}
@@ -0,0 +1,313 @@
+use std::net::IpAddr;
+use std::str::FromStr;
+
+use hyper::Uri;
+
+use super::{Error, JobCreate, JobProtocol, util::split};
+
+/// Private, loopback, link-local, metadata and other non public ranges, blocked
+/// unless `--allow-private-destinations` or `--destination-allow` says otherwise
+const BLOCKED_RANGES: [&str; 17] = [
+    "0.0.0.0/8",
+    "10.0.0.0/8",
+    "100.64.0.0/10",
+    "127.0.0.0/8",
+    "169.254.0.0/16",
+    "172.16.0.0/12",
+    "192.0.0.0/24",
+    "192.168.0.0/16",
+    "198.18.0.0/15",
+    "224.0.0.0/4",
+    "240.0.0.0/4",
+    "::/128",
+    "::1/128",
+    "64:ff9b::/96",
+    "fc00::/7",
+    "fe80::/10",
+    "ff00::/8",
+];
+
+/// `10.0.0.0/8`, a single address is a `/32` or `/128`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cidr {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.addr, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl std::fmt::Display for Cidr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix)
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr, prefix) = match s.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (s, None),
+        };
+        let addr: IpAddr = addr
+            .parse()
+            .map_err(|_| Error::InvalidParams("destination"))?;
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(prefix) => prefix
+                .parse::<u8>()
+                .ok()
+                .filter(|&p| p <= max)
+                .ok_or(Error::InvalidParams("destination"))?,
+            None => max,
+        };
+        Ok(Cidr { addr, prefix })
+    }
+}
+
+/// Entry of `--destination-allow`, `--destination-deny`, `hosts` of TLS profiles and secrets
+#[derive(Debug, Clone, PartialEq)]
+pub enum HostRule {
+    Name(String),
+    /// `*.example.com`, matches subdomains only
+    Suffix(String),
+    Net(Cidr),
+}
+
+impl HostRule {
+    pub fn parse(s: &str) -> Result<HostRule, Error> {
+        let s = s.trim().trim_end_matches('.').to_ascii_lowercase();
+        if s.is_empty() {
+            return Err(Error::InvalidParams("destination"));
+        }
+        if let Some(domain) = s.strip_prefix("*.") {
+            return Ok(HostRule::Suffix(format!(".{}", domain)));
+        }
+        if s.parse::<IpAddr>().is_ok() || s.contains('/') {
+            return Ok(HostRule::Net(s.parse()?));
+        }
+        Ok(HostRule::Name(s))
+    }
+
+    fn matches_name(&self, name: &str) -> bool {
+        match self {
+            HostRule::Name(rule) => rule == name,
+            HostRule::Suffix(suffix) => name.ends_with(suffix.as_str()),
+            HostRule::Net(_) => false,
+        }
+    }
+
+    fn matches_ip(&self, ip: IpAddr) -> bool {
+        match self {
+            HostRule::Net(cidr) => cidr.contains(ip),
+            _ => false,
+        }
+    }
+
+    /// Host of a url, a name or an address literal
+    pub fn matches_host(&self, host: &str) -> bool {
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        match host.parse::<IpAddr>() {
+            Ok(ip) => self.matches_ip(ip),
+            Err(_) => self.matches_name(&host.trim_end_matches('.').to_ascii_lowercase()),
+        }
+    }
+}
+
+/// Which hosts, addresses and ports jobs may call, checked on enqueue and
+/// again on connect for the resolved addresses against DNS rebinding
+#[derive(Debug, Clone)]
+pub struct DestinationPolicy {
+    allow: Vec<HostRule>,
+    deny: Vec<HostRule>,
+    /// Inclusive port ranges, any port when empty
+    ports: Vec<(u16, u16)>,
+    deny_ports: Vec<(u16, u16)>,
+    blocked: Vec<Cidr>,
+}
+
+fn parse_ports(list: &str) -> Result<Vec<(u16, u16)>, Error> {
+    split(list)
+        .map(|s| {
+            let (from, to) = s.split_once('-').unwrap_or((s, s));
+            match (from.trim().parse::<u16>(), to.trim().parse::<u16>()) {
+                (Ok(from), Ok(to)) if from <= to => Ok((from, to)),
+                _ => Err(Error::InvalidParams("destination port")),
+            }
+        })
+        .collect()
+}
+
+impl DestinationPolicy {
+    /// Comma separated lists, hosts, `*.` domains, addresses and CIDRs for
+    /// `allow` and `deny`, ports and `8000-8999` ranges for `ports` and `deny_ports`
+    pub fn new(
+        allow: &str,
+        deny: &str,
+        ports: &str,
+        deny_ports: &str,
+        allow_private: bool,
+    ) -> Result<Self, Error> {
+        let blocked = match allow_private {
+            true => Vec::new(),
+            false => BLOCKED_RANGES
+                .iter()
+                .map(|range| range.parse())
+                .collect::<Result<_, _>>()?,
+        };
+        Ok(Self {
+            allow: split(allow)
+                .map(HostRule::parse)
+                .collect::<Result<_, _>>()?,
+            deny: split(deny).map(HostRule::parse).collect::<Result<_, _>>()?,
+            ports: parse_ports(ports)?,
+            deny_ports: parse_ports(deny_ports)?,
+            blocked,
+        })
+    }
+
+    /// Host and port of a destination url, names are checked again with their
+    /// addresses on connect by `check_ip`
+    pub fn check_uri(&self, uri: &Uri) -> Result<(), Error> {
+        let host = uri.host().ok_or(Error::InvalidUrl)?;
+        let port = uri.port_u16().unwrap_or(match uri.scheme_str() {
+            Some("https") => 443,
+            _ => 80,
+        });
+        self.check_port(port)
+            .and_then(|_| {
+                let host = host.trim_start_matches('[').trim_end_matches(']');
+                match host.parse::<IpAddr>() {
+                    Ok(ip) => self.check_ip(None, ip),
+                    Err(_) => self.check_name(host),
+                }
+            })
+            .map_err(Error::DestinationBlocked)
+    }
+
+    pub fn check_job(&self, job: &JobCreate) -> Result<(), Error> {
+        match &job.meta.protocol {
+            JobProtocol::Http(http) => self.check_uri(&http.url),
+            JobProtocol::None => Ok(()),
+        }
+    }
+
+    fn check_port(&self, port: u16) -> Result<(), String> {
+        let within = |ranges: &[(u16, u16)]| ranges.iter().any(|&(f, t)| f <= port && port <= t);
+        if within(&self.deny_ports) || !(self.ports.is_empty() || within(&self.ports)) {
+            return Err(format!("port {} is not allowed", port));
+        }
+        Ok(())
+    }
+
+    fn check_name(&self, name: &str) -> Result<(), String> {
+        let name = name.trim_end_matches('.').to_ascii_lowercase();
+        if self.deny.iter().any(|rule| rule.matches_name(&name)) {
+            return Err(format!("host {} is denied", name));
+        }
+        let loopback = name == "localhost" || name.ends_with(".localhost");
+        if loopback && !self.blocked.is_empty() && !self.allows_name(&name) {
+            return Err(format!("host {} is a loopback address", name));
+        }
+        Ok(())
+    }
+
+    fn allows_name(&self, name: &str) -> bool {
+        self.allow.iter().any(|rule| rule.matches_name(name))
+    }
+
+    /// An address of `name`, hosts of `--destination-allow` may resolve to private addresses
+    pub fn check_ip(&self, name: Option<&str>, ip: IpAddr) -> Result<(), String> {
+        let ip = ip.to_canonical();
+        if self.deny.iter().any(|rule| rule.matches_ip(ip)) {
+            return Err(format!("{} is denied", ip));
+        }
+        let allowed = name.is_some_and(|name| self.allows_name(&name.to_ascii_lowercase()))
+            || self.allow.iter().any(|rule| rule.matches_ip(ip));
+        if allowed {
+            return Ok(());
+        }
+        if let Some(range) = self.blocked.iter().find(|range| range.contains(ip)) {
+            return Err(format!(
+                "{} is a private, loopback, link-local or metadata address ({})",
+                ip, range
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn destination_policy_blocks_private_ranges() -> anyhow::Result<()> {
+    // arrange
+    let policy = DestinationPolicy::new("*.svc.local,10.1.0.0/16", "evil.com", "", "25", false)?;
+    let check = |url: &str| policy.check_uri(&url.parse::<Uri>().unwrap()).is_ok();
+
+    // act & assert
+    assert!(check("https://example.com/hook"));
+    assert!(!check("http://169.254.169.254/latest/meta-data"));
+    assert!(!check("http://localhost:5432/"));
+    assert!(!check("http://[::1]:8080/"));
+    assert!(!check("http://[::ffff:127.0.0.1]/"));
+    assert!(!check("http://evil.com/"));
+    assert!(!check("http://example.com:25/"));
+    assert!(check("http://10.1.2.3/"));
+    assert!(!check("http://10.2.0.1/"));
+    assert!(
+        policy
+            .check_ip(Some("api.svc.local"), "10.9.9.9".parse()?)
+            .is_ok()
+    );
+    assert!(
+        policy
+            .check_ip(Some("rebind.example.com"), "127.0.0.1".parse()?)
+            .is_err()
+    );
+    assert!(
+        DestinationPolicy::new("", "", "", "", true)?
+            .check_uri(&"http://localhost/".parse()?)
+            .is_ok()
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn destination_policy_ports() -> anyhow::Result<()> {
+    // arrange
+    let policy = DestinationPolicy::new("", "", "80,443,8000-8999", "", false)?;
+
+    // act & assert
+    assert!(policy.check_uri(&"https://example.com/".parse()?).is_ok());
+    assert!(
+        policy
+            .check_uri(&"http://example.com:8080/".parse()?)
+            .is_ok()
+    );
+    assert!(
+        policy
+            .check_uri(&"http://example.com:9000/".parse()?)
+            .is_err()
+    );
+    assert!(DestinationPolicy::new("", "", "80-", "", false).is_err());
+    assert!(DestinationPolicy::new("10.0.0.0/33", "", "", "", false).is_err());
+    Ok(())
+}
@@ -0,0 +1,44 @@
+use super::Error;
+
+/// Parses a duration like `90`, `30s`, `15m`, `12h` or `7d` into seconds.
+pub fn parse_duration_secs(s: &str) -> Result<u32, Error> {
+    let s = s.trim();
+    let (value, unit) = match s.find(|c: char| !c.is_ascii_digit()) {
+        Some(idx) => s.split_at(idx),
+        None => (s, ""),
+    };
+    let value: u32 = value
+        .parse()
+        .map_err(|_| Error::InvalidParams("duration"))?;
+    let multiplier: u32 = match unit {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 60 * 60 * 24,
+        _ => return Err(Error::InvalidParams("duration")),
+    };
+    value
+        .checked_mul(multiplier)
+        .ok_or(Error::InvalidParams("duration"))
+}
+
+#[tokio::test]
+async fn parse_duration_secs_ok() -> anyhow::Result<()> {
+    // act & assert
+    assert_eq!(90, parse_duration_secs("90")?);
+    assert_eq!(30, parse_duration_secs("30s")?);
+    assert_eq!(15 * 60, parse_duration_secs("15m")?);
+    assert_eq!(12 * 60 * 60, parse_duration_secs("12h")?);
+    assert_eq!(7 * 60 * 60 * 24, parse_duration_secs("7d")?);
+    Ok(())
+}
+
+#[tokio::test]
+async fn parse_duration_secs_err() -> anyhow::Result<()> {
+    // act & assert
+    assert!(parse_duration_secs("").is_err());
+    assert!(parse_duration_secs("m").is_err());
+    assert!(parse_duration_secs("10w").is_err());
+    assert!(parse_duration_secs("-5s").is_err());
+    Ok(())
+}
@@ -0,0 +1,245 @@
+use std::collections::HashMap;
+
+use aes_gcm::{
+    Aes256Gcm, Nonce,
+    aead::{Aead, AeadCore, KeyInit, OsRng, Payload},
+};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    Error,
+    util::{Sensitive, split},
+};
+
+/// Env variable of the master keys, `id:base64,id:base64`, the first one encrypts
+pub const ENCRYPTION_KEYS_ENV: &str = "IRISQO_ENCRYPTION_KEYS";
+
+const NONCE_LEN: usize = 12;
+const BODY_AAD: &[u8] = b"body";
+const HEADERS_AAD: &[u8] = b"headers";
+
+/// Data key of a row sealed by a master key, stored in `envelope` of `jobs` and `processed`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Envelope {
+    pub key_id: String,
+    /// Sealed data key, base64
+    pub dek: String,
+    /// Sealed JSON of the headers, base64, the `headers` column is then empty
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub headers: Option<String>,
+}
+
+/// AES-256-GCM key of a single row, sealed data is the nonce followed by the ciphertext
+pub struct DataKey(Aes256Gcm);
+
+impl DataKey {
+    fn seal(&self, data: &[u8], aad: &[u8]) -> Result<Vec<u8>, Error> {
+        seal(&self.0, data, aad)
+    }
+
+    fn open(&self, data: &[u8], aad: &[u8]) -> Result<Vec<u8>, Error> {
+        open(&self.0, data, aad)
+    }
+}
+
+fn seal(cipher: &Aes256Gcm, data: &[u8], aad: &[u8]) -> Result<Vec<u8>, Error> {
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let sealed = cipher
+        .encrypt(&nonce, Payload { msg: data, aad })
+        .map_err(|_| Error::Encryption("seal"))?;
+    Ok([nonce.as_slice(), &sealed].concat())
+}
+
+fn open(cipher: &Aes256Gcm, data: &[u8], aad: &[u8]) -> Result<Vec<u8>, Error> {
+    if data.len() < NONCE_LEN {
+        return Err(Error::Encryption("sealed data too short"));
+    }
+    let (nonce, sealed) = data.split_at(NONCE_LEN);
+    cipher
+        .decrypt(Nonce::from_slice(nonce), Payload { msg: sealed, aad })
+        .map_err(|_| Error::Encryption("open"))
+}
+
+/// Master keys of the envelope encryption, none disables it. Only data keys are
+/// sealed by a master key, so a rotation rewraps envelopes without touching bodies.
+#[derive(Debug, Clone, Default)]
+pub struct EncryptionKeys {
+    keys: Vec<(String, Sensitive<Aes256Gcm>)>,
+}
+
+impl EncryptionKeys {
+    /// Comma separated `id:base64` of 32 byte keys
+    pub fn new(list: &str) -> Result<Self, Error> {
+        let mut keys: Vec<(String, Sensitive<Aes256Gcm>)> = Vec::new();
+        for entry in split(list) {
+            let (id, key) = entry
+                .split_once(':')
+                .ok_or(Error::InvalidParams("encryption key"))?;
+            let key = STANDARD
+                .decode(key.trim())
+                .map_err(|_| Error::InvalidParams("encryption key"))?;
+            let cipher = Aes256Gcm::new_from_slice(&key)
+                .map_err(|_| Error::InvalidParams("encryption key"))?;
+            let id = id.trim();
+            if id.is_empty() || keys.iter().any(|(other, _)| other == id) {
+                return Err(Error::InvalidParams("encryption key id"));
+            }
+            keys.push((id.to_string(), Sensitive(cipher)));
+        }
+        Ok(Self { keys })
+    }
+
+    pub fn from_env() -> Result<Self, Error> {
+        Self::new(&std::env::var(ENCRYPTION_KEYS_ENV).unwrap_or_default())
+    }
+
+    /// Id of the key sealing new data keys
+    pub fn current_id(&self) -> Option<&str> {
+        self.keys.first().map(|(id, _)| id.as_str())
+    }
+
+    fn cipher(&self, key_id: &str) -> Result<&Aes256Gcm, Error> {
+        self.keys
+            .iter()
+            .find(|(id, _)| id == key_id)
+            .map(|(_, cipher)| &cipher.0)
+            .ok_or(Error::Encryption("unknown key id"))
+    }
+
+    /// Fresh data key and its envelope, `None` without keys
+    fn new_data_key(&self) -> Result<Option<(DataKey, Envelope)>, Error> {
+        let Some((key_id, cipher)) = self.keys.first() else {
+            return Ok(None);
+        };
+        let key = Aes256Gcm::generate_key(&mut OsRng);
+        let dek = seal(cipher, &key, key_id.as_bytes())?;
+        let envelope = Envelope {
+            key_id: key_id.clone(),
+            dek: STANDARD.encode(dek),
+            headers: None,
+        };
+        Ok(Some((DataKey(Aes256Gcm::new(&key)), envelope)))
+    }
+
+    /// Raw data key of `envelope`
+    fn open_dek(&self, envelope: &Envelope) -> Result<Vec<u8>, Error> {
+        let dek = STANDARD
+            .decode(&envelope.dek)
+            .map_err(|_| Error::Encryption("dek"))?;
+        open(
+            self.cipher(&envelope.key_id)?,
+            &dek,
+            envelope.key_id.as_bytes(),
+        )
+    }
+
+    fn data_key(&self, envelope: &Envelope) -> Result<DataKey, Error> {
+        Aes256Gcm::new_from_slice(&self.open_dek(envelope)?)
+            .map(DataKey)
+            .map_err(|_| Error::Encryption("dek"))
+    }
+
+    /// Encrypts a body in place and moves the headers into the envelope
+    pub fn seal(
+        &self,
+        body: &mut Bytes,
+        headers: &mut Option<HashMap<String, String>>,
+    ) -> Result<Option<Envelope>, Error> {
+        if body.is_empty() && headers.is_none() {
+            return Ok(None);
+        }
+        let Some((key, mut envelope)) = self.new_data_key()? else {
+            return Ok(None);
+        };
+        if !body.is_empty() {
+            *body = Bytes::from(key.seal(body, BODY_AAD)?);
+        }
+        if let Some(map) = headers.take() {
+            let json = serde_json::to_vec(&map).map_err(|_| Error::Encryption("headers"))?;
+            envelope.headers = Some(STANDARD.encode(key.seal(&json, HEADERS_AAD)?));
+        }
+        Ok(Some(envelope))
+    }
+
+    /// Decrypts a loaded body and the headers in place, `envelope` is cleared
+    pub fn open(
+        &self,
+        body: &mut Option<Vec<u8>>,
+        headers: &mut Option<HashMap<String, String>>,
+        envelope: &mut Option<Envelope>,
+    ) -> Result<(), Error> {
+        let Some(envelope) = envelope.take() else {
+            return Ok(());
+        };
+        let key = self.data_key(&envelope)?;
+        if let Some(data) = body.as_deref().filter(|data| !data.is_empty()) {
+            *body = Some(key.open(data, BODY_AAD)?);
+        }
+        if let Some(sealed) = &envelope.headers {
+            let sealed = STANDARD
+                .decode(sealed)
+                .map_err(|_| Error::Encryption("headers"))?;
+            let json = key.open(&sealed, HEADERS_AAD)?;
+            *headers =
+                Some(serde_json::from_slice(&json).map_err(|_| Error::Encryption("headers"))?);
+        }
+        Ok(())
+    }
+
+    /// Seals the data key of `envelope` with the current key, `None` when it already is
+    pub fn rewrap(&self, envelope: &Envelope) -> Result<Option<Envelope>, Error> {
+        let Some((key_id, cipher)) = self.keys.first() else {
+            return Err(Error::Encryption("no encryption key"));
+        };
+        if envelope.key_id == *key_id {
+            return Ok(None);
+        }
+        let key = self.open_dek(envelope)?;
+        Ok(Some(Envelope {
+            key_id: key_id.clone(),
+            dek: STANDARD.encode(seal(cipher, &key, key_id.as_bytes())?),
+            headers: envelope.headers.clone(),
+        }))
+    }
+}
+
+#[tokio::test]
+async fn encryption_keys_seal_open_rewrap() -> anyhow::Result<()> {
+    // arrange
+    let (old, new) = (STANDARD.encode([1u8; 32]), STANDARD.encode([2u8; 32]));
+    let keys = EncryptionKeys::new(&format!("k1:{}", old))?;
+    let rotated = EncryptionKeys::new(&format!("k2:{},k1:{}", new, old))?;
+    let headers = HashMap::from([("authorization".to_string(), "Bearer secret".to_string())]);
+    let mut body = Bytes::from_static(b"{\"card\":\"4111\"}");
+    let mut sealed_headers = Some(headers.clone());
+
+    // act
+    let envelope = keys.seal(&mut body, &mut sealed_headers)?;
+    let rewrapped = rotated.rewrap(envelope.as_ref().unwrap())?;
+    let mut opened = Some(body.to_vec());
+    let mut opened_headers = None;
+    rotated.open(&mut opened, &mut opened_headers, &mut rewrapped.clone())?;
+
+    // assert
+    assert_eq!(Some("k1"), envelope.as_ref().map(|e| e.key_id.as_str()));
+    assert_eq!(None, sealed_headers);
+    assert!(!body.windows(4).any(|w| w == b"4111"));
+    assert_eq!(Some("k2"), rewrapped.as_ref().map(|e| e.key_id.as_str()));
+    assert_eq!(None, rotated.rewrap(rewrapped.as_ref().unwrap())?);
+    assert_eq!(Some(b"{\"card\":\"4111\"}".to_vec()), opened);
+    assert_eq!(Some(headers), opened_headers);
+    assert!(
+        keys.open(&mut Some(body.to_vec()), &mut None, &mut rewrapped.clone())
+            .is_err()
+    );
+    assert!(
+        EncryptionKeys::default()
+            .seal(&mut body, &mut None)?
+            .is_none()
+    );
+    assert!(EncryptionKeys::new("k1:c2hvcnQ=").is_err());
+    assert!(!format!("{:?}", keys).contains(&old));
+    Ok(())
+}
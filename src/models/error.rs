@@ -1,5 +1,6 @@
 use axum::http::StatusCode;
 use problemdetails::Problem;
+use serde::Serialize;
 use tokio::time::error::Elapsed;
 
 use crate::{features::results::JobResult, otel};
@@ -13,6 +14,15 @@ pub enum Error {
     #[error("Job Not Found - {0}")]
     JobNotFound(i64),
 
+    #[error("Job Not Found - external_id {0}")]
+    ExternalIdNotFound(String),
+
+    #[error("Secret Not Found - {0}")]
+    SecretNotFound(String),
+
+    #[error("Job Already Exists - external_id {0}")]
+    ExternalIdConflict(String),
+
     #[error(transparent)]
     Timeout(#[from] Elapsed),
 
@@ -34,9 +44,33 @@ pub enum Error {
     #[error("Invalid Url")]
     InvalidUrl,
 
+    #[error("Destination Blocked - {0}")]
+    DestinationBlocked(String),
+
+    #[error("TLS Profile - {0}")]
+    TlsProfile(String),
+
     #[error("Invalid Params - {0}")]
     InvalidParams(&'static str),
 
+    #[error("Validation Failed")]
+    Validation(Vec<ValidationError>),
+
+    #[error("Payload Too Large - max {0} bytes")]
+    BodyTooLarge(usize),
+
+    #[error("Unauthorized - missing or invalid API key")]
+    Unauthorized,
+
+    #[error("Forbidden - requires scope {0}")]
+    Forbidden(&'static str),
+
+    #[error("Encryption Failed - {0}")]
+    Encryption(&'static str),
+
+    #[error("Quota Exceeded - {0} limit is {1}")]
+    QuotaExceeded(&'static str, i64),
+
     #[error("Server Error")]
     ServerError(JobResult),
 
@@ -47,6 +81,21 @@ pub enum Error {
     RetriesExceeded,
 }
 
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ValidationError {
+    pub field: &'static str,
+    pub detail: String,
+}
+
+impl ValidationError {
+    pub fn new(field: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            field,
+            detail: detail.into(),
+        }
+    }
+}
+
 impl From<Error> for Problem {
     fn from(item: Error) -> Problem {
         let trace_id = otel::current_trace_id();
@@ -56,11 +105,54 @@ impl From<Error> for Problem {
                 .with_title(StatusCode::BAD_REQUEST.to_string())
                 .with_detail(item.to_string())
                 .with_value("trace_id", trace_id),
+            Error::DestinationBlocked(_) => problemdetails::new(StatusCode::BAD_REQUEST)
+                .with_title(StatusCode::BAD_REQUEST.to_string())
+                .with_detail(item.to_string())
+                .with_value("trace_id", trace_id),
+            Error::TlsProfile(_) => problemdetails::new(StatusCode::BAD_REQUEST)
+                .with_title(StatusCode::BAD_REQUEST.to_string())
+                .with_detail(item.to_string())
+                .with_value("trace_id", trace_id),
             Error::InvalidParams(_) => problemdetails::new(StatusCode::BAD_REQUEST)
                 // .with_type("https://example.com/probs/out-of-credit")
                 .with_title(StatusCode::BAD_REQUEST.to_string())
                 .with_detail(item.to_string())
                 .with_value("trace_id", trace_id),
+            Error::JobNotFound(_) | Error::ExternalIdNotFound(_) => {
+                problemdetails::new(StatusCode::NOT_FOUND)
+                    .with_title(StatusCode::NOT_FOUND.to_string())
+                    .with_detail(item.to_string())
+                    .with_value("trace_id", trace_id)
+            }
+            Error::SecretNotFound(_) => problemdetails::new(StatusCode::NOT_FOUND)
+                .with_title(StatusCode::NOT_FOUND.to_string())
+                .with_detail(item.to_string())
+                .with_value("trace_id", trace_id),
+            Error::ExternalIdConflict(_) => problemdetails::new(StatusCode::CONFLICT)
+                .with_title(StatusCode::CONFLICT.to_string())
+                .with_detail(item.to_string())
+                .with_value("trace_id", trace_id),
+            Error::Validation(ref errors) => problemdetails::new(StatusCode::BAD_REQUEST)
+                .with_title(StatusCode::BAD_REQUEST.to_string())
+                .with_detail(item.to_string())
+                .with_value("errors", serde_json::to_value(errors).unwrap_or_default())
+                .with_value("trace_id", trace_id),
+            Error::BodyTooLarge(_) => problemdetails::new(StatusCode::PAYLOAD_TOO_LARGE)
+                .with_title(StatusCode::PAYLOAD_TOO_LARGE.to_string())
+                .with_detail(item.to_string())
+                .with_value("trace_id", trace_id),
+            Error::Unauthorized => problemdetails::new(StatusCode::UNAUTHORIZED)
+                .with_title(StatusCode::UNAUTHORIZED.to_string())
+                .with_detail(item.to_string())
+                .with_value("trace_id", trace_id),
+            Error::Forbidden(_) => problemdetails::new(StatusCode::FORBIDDEN)
+                .with_title(StatusCode::FORBIDDEN.to_string())
+                .with_detail(item.to_string())
+                .with_value("trace_id", trace_id),
+            Error::QuotaExceeded(..) => problemdetails::new(StatusCode::TOO_MANY_REQUESTS)
+                .with_title(StatusCode::TOO_MANY_REQUESTS.to_string())
+                .with_detail(item.to_string())
+                .with_value("trace_id", trace_id),
             Error::DbError(sqlx::Error::RowNotFound) => problemdetails::new(StatusCode::NOT_FOUND)
                 // .with_type("https://example.com/probs/out-of-credit")
                 .with_title(StatusCode::NOT_FOUND.to_string())
@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+
+use axum::http::HeaderMap;
+
+/// Incoming `Irisqo-Forward-Name: value` is sent to the destination as `Name: value`
+pub const FORWARD_PREFIX: &str = "irisqo-forward-";
+/// Incoming `Irisqo-Delay: 60` is the same as `?_delay=60`
+pub const OPTION_PREFIX: &str = "irisqo-";
+/// Headers forwarded without prefix unless `--forward-headers` is set
+pub const DEFAULT_ALLOW_HEADERS: &str = "content-type";
+/// Headers never forwarded unless `--deny-headers` is set, `*` suffix matches a prefix
+pub const DEFAULT_DENY_HEADERS: &str = "forwarded,x-forwarded-*,x-real-ip";
+/// Connection level headers, always denied
+const HOP_BY_HOP_HEADERS: [&str; 10] = [
+    "connection",
+    "content-length",
+    "keep-alive",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "proxy-connection",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+];
+
+/// Which incoming headers are stored with a job and sent to the destination
+#[derive(Debug, Clone)]
+pub struct ForwardOptions {
+    allow: Vec<String>,
+    deny: Vec<String>,
+}
+
+impl ForwardOptions {
+    /// Comma separated, case insensitive header names
+    pub fn new(allow: &str, deny: &str) -> Self {
+        let split = |s: &str| {
+            s.split(',')
+                .map(|h| h.trim().to_ascii_lowercase())
+                .filter(|h| !h.is_empty())
+                .collect()
+        };
+        Self {
+            allow: split(allow),
+            deny: split(deny),
+        }
+    }
+
+    pub fn is_denied(&self, name: &str) -> bool {
+        let name = name.to_ascii_lowercase();
+        HOP_BY_HOP_HEADERS.contains(&name.as_str())
+            || self.deny.iter().any(|d| match d.strip_suffix('*') {
+                Some(prefix) => name.starts_with(prefix),
+                None => *d == name,
+            })
+    }
+
+    /// `Irisqo-Forward-` headers with the prefix stripped and allowed headers as is,
+    /// a prefixed header wins over an allowed one with the same name
+    pub fn forward(&self, headers: &HeaderMap) -> HashMap<String, String> {
+        let mut forwarded = HashMap::new();
+        for (name, value) in headers {
+            let Ok(value) = value.to_str() else {
+                continue;
+            };
+            let name = name.as_str();
+            let (name, prefixed) = match name.strip_prefix(FORWARD_PREFIX) {
+                Some(stripped) => (stripped, true),
+                None if self.allow.iter().any(|a| a == name) => (name, false),
+                None => continue,
+            };
+            if name.is_empty() || self.is_denied(name) {
+                continue;
+            }
+            if prefixed || !forwarded.contains_key(name) {
+                forwarded.insert(name.to_string(), value.to_owned());
+            }
+        }
+        forwarded
+    }
+
+    /// Removes denied headers set explicitly, e.g. in a JSON envelope
+    pub fn retain(&self, headers: &mut HashMap<String, String>) {
+        headers.retain(|name, _| !self.is_denied(name));
+    }
+}
+
+impl Default for ForwardOptions {
+    fn default() -> Self {
+        Self::new(DEFAULT_ALLOW_HEADERS, DEFAULT_DENY_HEADERS)
+    }
+}
+
+/// `_` options given as `Irisqo-*` headers, e.g. `Irisqo-Delay-Until` as `_delay_until`
+pub fn header_options(headers: &HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .filter_map(|(name, value)| {
+            let option = name.as_str().strip_prefix(OPTION_PREFIX)?;
+            if name.as_str().starts_with(FORWARD_PREFIX) || option.is_empty() {
+                return None;
+            }
+            let value = value.to_str().ok()?;
+            Some((format!("_{}", option.replace('-', "_")), value.to_owned()))
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn forward_options_forward() -> anyhow::Result<()> {
+    // arrange
+    let options = ForwardOptions::default();
+    let mut headers = HeaderMap::new();
+    headers.insert("content-type", "text/plain".parse()?);
+    headers.insert("authorization", "Bearer irisqo".parse()?);
+    headers.insert("x-forwarded-for", "10.0.0.1".parse()?);
+    headers.insert("irisqo-forward-authorization", "Bearer dest".parse()?);
+    headers.insert("irisqo-forward-x-forwarded-host", "example.com".parse()?);
+    headers.insert("irisqo-forward-transfer-encoding", "chunked".parse()?);
+    headers.insert("irisqo-delay", "60".parse()?);
+
+    // act
+    let forwarded = options.forward(&headers);
+
+    // assert
+    assert_eq!(2, forwarded.len());
+    assert_eq!(
+        Some("text/plain"),
+        forwarded.get("content-type").map(String::as_str)
+    );
+    assert_eq!(
+        Some("Bearer dest"),
+        forwarded.get("authorization").map(String::as_str)
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn header_options_ok() -> anyhow::Result<()> {
+    // arrange
+    let mut headers = HeaderMap::new();
+    headers.insert("irisqo-delay-until", "1700000000".parse()?);
+    headers.insert("irisqo-forward-x-api-key", "secret".parse()?);
+    headers.insert("x-delay", "60".parse()?);
+
+    // act
+    let options = header_options(&headers);
+
+    // assert
+    assert_eq!(
+        vec![("_delay_until".to_string(), "1700000000".to_string())],
+        options
+    );
+    Ok(())
+}
@@ -1,4 +1,4 @@
-use std::{collections::HashMap, time::SystemTime};
+use std::{collections::HashMap, str::FromStr, time::SystemTime};
 
 use bytes::Bytes;
 use http_body_util::Full;
@@ -7,10 +7,11 @@ use hyper::HeaderMap;
 use hyper::{Method, Uri, header};
 
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
 use crate::features::schedules::JobSchedule;
 
-use super::{Error, JobRetry};
+use super::{BodyCodec, Capture, Envelope, Error, JobRetry};
 
 #[derive(Debug, Clone, sqlx::FromRow, Serialize)]
 pub struct JobRow {
@@ -22,6 +23,43 @@ pub struct JobRow {
     pub body: Option<Vec<u8>>,
     pub schedule_id: Option<String>,
     pub external_id: Option<String>,
+    /// Key of the body in the blob store when not inlined
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body_ref: Option<String>,
+    /// `zstd` or `gzip` when the stored body is compressed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body_codec: Option<String>,
+    pub tenant_id: String,
+    /// Set while the body and headers are sealed, opened before use
+    #[sqlx(json(nullable))]
+    #[serde(skip)]
+    pub envelope: Option<Envelope>,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow, Serialize)]
+pub struct JobListRow {
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub job: JobRow,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub state: Option<String>,
+    pub retry: i32,
+    pub processed_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct JobSearch {
+    /// scheduled, enqueued, assigned, completed, failed or cancelled
+    pub state: Option<String>,
+    pub host: Option<String>,
+    pub method: Option<String>,
+    pub schedule_id: Option<String>,
+    pub external_id_prefix: Option<String>,
+    pub created_from: Option<chrono::DateTime<chrono::Utc>>,
+    pub created_to: Option<chrono::DateTime<chrono::Utc>>,
+    pub processed_from: Option<chrono::DateTime<chrono::Utc>>,
+    pub processed_to: Option<chrono::DateTime<chrono::Utc>>,
+    pub min_retry: Option<i32>,
 }
 
 #[derive(Debug, Clone, Copy, sqlx::FromRow)]
@@ -37,18 +75,88 @@ pub struct JobWithRetry {
     pub retry: i32,
 }
 
+/// Tenant of requests without a key, with `--no-auth` or on public routes
+pub const DEFAULT_TENANT: &str = "default";
+
 #[derive(Debug, Clone, Default)]
 pub struct JobCreate {
+    /// Pre-allocated id, seeds the jitter of one-off jobs without an external id
+    pub id: Option<i64>,
     pub meta: JobMeta,
     pub headers: Option<HashMap<String, String>>,
     pub body: Bytes,
+    /// Set when `body` was moved to the blob store
+    pub body_ref: Option<String>,
+    /// Set when `body` is compressed
+    pub body_codec: Option<BodyCodec>,
+    /// Set when `body` and `headers` are sealed
+    pub envelope: Option<Envelope>,
     pub at: Option<i64>,
     pub schedule: Option<JobSchedule>,
     pub until: Option<i64>,
+    pub anchor: Option<i64>,
     pub external_id: Option<String>,
+    /// Dedup window of `external_id` in seconds, `None` is forever
+    pub external_id_ttl: Option<u32>,
+    pub id_conflict: IdConflict,
+}
+
+impl JobCreate {
+    /// Dedup key from the method, url, selected headers and body, fits `jobs.external_id`
+    pub fn content_key(&self, header_names: &[String]) -> Option<String> {
+        let JobProtocol::Http(meta) = &self.meta.protocol else {
+            return None;
+        };
+        let mut hasher = Sha256::new();
+        hasher.update(meta.method.as_str());
+        hasher.update(b"\n");
+        hasher.update(meta.url.to_string());
+        hasher.update(b"\n");
+        let mut header_names: Vec<String> = header_names.iter().map(|h| h.to_lowercase()).collect();
+        header_names.sort();
+        header_names.dedup();
+        for name in header_names {
+            let value = self.headers.as_ref().and_then(|h| h.get(&name));
+            if let Some(value) = value {
+                hasher.update(format!("{}:{}\n", name, value));
+            }
+        }
+        hasher.update(b"\n");
+        hasher.update(&self.body);
+        let hash = hex::encode(hasher.finalize());
+        Some(format!(
+            "{}{}",
+            CONTENT_KEY_PREFIX,
+            &hash[..64 - CONTENT_KEY_PREFIX.len()]
+        ))
+    }
+}
+
+const CONTENT_KEY_PREFIX: &str = "content:";
+
+/// What to do when a job with the same external id exists within the dedup window
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum IdConflict {
+    #[default]
+    ReturnExisting,
+    Reject,
+    ReplaceIfPending,
+}
+
+impl FromStr for IdConflict {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "return_existing" => Ok(IdConflict::ReturnExisting),
+            "reject_409" | "reject" => Ok(IdConflict::Reject),
+            "replace_if_pending" => Ok(IdConflict::ReplaceIfPending),
+            _ => Err(Error::InvalidParams("id_conflict")),
+        }
+    }
 }
 
-#[derive(Debug, sqlx::FromRow)]
+#[derive(Debug, sqlx::FromRow, Serialize)]
 pub struct JobCreateRow {
     pub id: i64,
     pub schedule_id: Option<String>,
@@ -63,9 +171,22 @@ pub struct JobMeta {
     pub retry: JobRetry,
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub delay: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub jitter: Option<u32>,
     pub timeout: u32,
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub trace_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub subscription_id: Option<String>,
+    /// Server `--capture` when not set
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub capture: Option<Capture>,
+    /// Max stored response body in bytes, server `--capture-max` when not set
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub capture_max: Option<u32>,
+    /// TLS profile, else the one matching the host
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub tls: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
@@ -80,6 +201,7 @@ pub enum JobProtocol {
     // RabbitMq,
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
 #[serde(rename_all = "snake_case")]
 pub enum JobDelay {
@@ -104,7 +226,8 @@ pub struct HttpMeta {
 impl TryFrom<JobRow> for hyper::Request<Full<Bytes>> {
     type Error = Error;
 
-    fn try_from(value: JobRow) -> Result<Self, Self::Error> {
+    fn try_from(mut value: JobRow) -> Result<Self, Self::Error> {
+        BodyCodec::decode(&mut value.body, &mut value.body_codec)?;
         let meta = value.meta;
         let JobProtocol::Http(meta) = meta.protocol else {
             return Err(Error::InvalidUrl);
@@ -135,6 +258,10 @@ async fn job_row_into_request_err() -> anyhow::Result<()> {
         body: None,
         schedule_id: None,
         external_id: None,
+        body_ref: None,
+        body_codec: None,
+        tenant_id: "default".into(),
+        envelope: None,
     };
     // act
     let req = hyper::Request::<Full<Bytes>>::try_from(job);
@@ -160,8 +287,13 @@ async fn job_row_into_request_ok() -> anyhow::Result<()> {
                 retry_delay: 1,
             },
             delay: Some(300),
+            jitter: None,
             timeout: 2000,
             trace_id: None,
+            subscription_id: None,
+            capture: None,
+            capture_max: None,
+            tls: None,
         },
         headers: Some(HashMap::from([(
             header::CONTENT_LENGTH.to_string(),
@@ -170,6 +302,10 @@ async fn job_row_into_request_ok() -> anyhow::Result<()> {
         body: None,
         schedule_id: None,
         external_id: None,
+        body_ref: None,
+        body_codec: None,
+        tenant_id: "default".into(),
+        envelope: None,
     };
     // act
     let req = hyper::Request::<Full<Bytes>>::try_from(job);
@@ -179,3 +315,53 @@ async fn job_row_into_request_ok() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn id_conflict_from_str() -> anyhow::Result<()> {
+    // act & assert
+    assert_eq!(IdConflict::ReturnExisting, "return_existing".parse()?);
+    assert_eq!(IdConflict::Reject, "reject_409".parse()?);
+    assert_eq!(IdConflict::ReplaceIfPending, "replace_if_pending".parse()?);
+    assert!("replace".parse::<IdConflict>().is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn job_create_content_key() -> anyhow::Result<()> {
+    // arrange
+    let job = JobCreate {
+        meta: JobMeta {
+            protocol: JobProtocol::Http(HttpMeta {
+                method: Method::POST,
+                url: Uri::try_from("http://localhost/hook").unwrap(),
+            }),
+            ..Default::default()
+        },
+        headers: Some(HashMap::from([
+            ("content-type".to_string(), "application/json".to_string()),
+            ("x-request-id".to_string(), "1".to_string()),
+        ])),
+        body: Bytes::from_static(b"{}"),
+        ..Default::default()
+    };
+    let mut other_request = job.clone();
+    other_request
+        .headers
+        .as_mut()
+        .unwrap()
+        .insert("x-request-id".to_string(), "2".to_string());
+    let mut other_body = job.clone();
+    other_body.body = Bytes::from_static(b"[]");
+    let selected = vec!["Content-Type".to_string()];
+
+    // act
+    let key = job.content_key(&selected).unwrap();
+
+    // assert
+    assert_eq!(64, key.len());
+    assert!(key.starts_with("content:"));
+    assert_eq!(Some(key.clone()), other_request.content_key(&selected));
+    assert_ne!(Some(key), other_body.content_key(&selected));
+    assert!(JobCreate::default().content_key(&selected).is_none());
+    Ok(())
+}
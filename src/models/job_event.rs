@@ -0,0 +1,50 @@
+use serde::{Deserialize, Serialize};
+
+use super::DEFAULT_TENANT;
+
+/// Job lifecycle event published for every insert into `history`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JobEvent {
+    pub id: i64,
+    pub retry: i32,
+    pub instance_id: String,
+    pub at: chrono::DateTime<chrono::Utc>,
+    pub status: String,
+    pub message: Option<String>,
+    pub schedule_id: Option<String>,
+    pub external_id: Option<String>,
+    /// Set for webhook delivery jobs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subscription_id: Option<String>,
+    /// Events are only published to the tenant of the job
+    #[serde(default = "default_tenant", skip_serializing)]
+    pub tenant_id: String,
+}
+
+impl JobEvent {
+    /// `message` of a failure with the retry policy exhausted
+    pub const DEAD_LETTERED: &str = "dead_lettered";
+
+    pub fn is_dead_lettered(&self) -> bool {
+        self.message.as_deref() == Some(Self::DEAD_LETTERED)
+    }
+}
+
+fn default_tenant() -> String {
+    DEFAULT_TENANT.to_string()
+}
+
+#[tokio::test]
+async fn job_event_from_notify_payload() -> anyhow::Result<()> {
+    // arrange
+    let payload = r#"{"id" : 42, "retry" : 1, "instance_id" : "host:01J", "at" : "2024-05-01T10:00:00.123456+00:00", "status" : "retried", "message" : null, "schedule_id" : null, "external_id" : "order-1"}"#;
+
+    // act
+    let event: JobEvent = serde_json::from_str(payload)?;
+
+    // assert
+    assert_eq!(42, event.id);
+    assert_eq!("retried", event.status);
+    assert_eq!(Some("order-1".to_string()), event.external_id);
+    Ok(())
+}
@@ -26,6 +26,16 @@ impl JobRetry {
         matches!(self, JobRetry::None)
     }
 
+    /// Max retries after the first attempt
+    pub const fn retry_count(&self) -> u16 {
+        match self {
+            JobRetry::None => 0,
+            JobRetry::Immediate { retry_count }
+            | JobRetry::Fixed { retry_count, .. }
+            | JobRetry::Fibonacci { retry_count, .. } => *retry_count,
+        }
+    }
+
     pub const fn fibonacci(idx: usize) -> u32 {
         if idx >= Self::FIB_ARRAY.len() {
             return Self::FIB_ARRAY[31];
@@ -1,17 +1,52 @@
+pub use body::{BlobStore, BodyEncoding, BodyOptions};
+pub use capture::Capture;
+pub use codec::BodyCodec;
+pub use connector::DestinationConnector;
+pub use destination::{DestinationPolicy, HostRule};
+pub use duration::parse_duration_secs;
+pub use encryption::{EncryptionKeys, Envelope};
 pub use error::Error;
+pub use error::ValidationError;
+pub use forward::{ForwardOptions, header_options};
 
+pub use job::DEFAULT_TENANT;
 pub use job::HttpMeta;
+pub use job::IdConflict;
 pub use job::JobCreate;
 pub use job::JobCreateRow;
 pub use job::JobEntry;
+pub use job::JobListRow;
 pub use job::JobMeta;
 pub use job::JobProtocol;
 pub use job::JobRow;
+pub use job::JobSearch;
 pub use job::JobWithRetry;
+pub use job_event::JobEvent;
 pub use jobretry::JobRetry;
+pub use notifier::{HISTORY_CHANNEL, JobNotifier, PROCESSED_CHANNEL};
+pub use proxy::ProxyRules;
+pub use redact::Redaction;
+pub use signing::SigningKeys;
 pub use state::AppState;
+pub use state::WorkerOptions;
+pub use tls::HttpClients;
 
+mod body;
+mod capture;
+mod codec;
+mod connector;
+mod destination;
+mod duration;
+mod encryption;
 mod error;
+mod forward;
 mod job;
+mod job_event;
 mod jobretry;
+mod notifier;
+mod proxy;
+mod redact;
+mod signing;
 mod state;
+mod tls;
+mod util;
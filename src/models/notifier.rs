@@ -0,0 +1,54 @@
+use tokio::sync::broadcast;
+
+use super::JobEvent;
+
+/// Postgres NOTIFY channel with the ids of processed jobs
+pub const PROCESSED_CHANNEL: &str = "irisqo_processed";
+/// Postgres NOTIFY channel with `history` inserts as JSON
+pub const HISTORY_CHANNEL: &str = "irisqo_history";
+
+/// In-process fan-out of processed job ids and job lifecycle events
+#[derive(Debug)]
+pub struct JobNotifier {
+    tx: broadcast::Sender<i64>,
+    events_tx: broadcast::Sender<JobEvent>,
+}
+
+impl JobNotifier {
+    pub fn new(capacity: usize) -> Self {
+        let (tx, _) = broadcast::channel(capacity);
+        let (events_tx, _) = broadcast::channel(capacity);
+        Self { tx, events_tx }
+    }
+
+    pub fn notify(&self, job_id: i64) {
+        // No receivers is not an error
+        _ = self.tx.send(job_id);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<i64> {
+        self.tx.subscribe()
+    }
+
+    pub fn publish(&self, event: JobEvent) {
+        _ = self.events_tx.send(event);
+    }
+
+    pub fn subscribe_events(&self) -> broadcast::Receiver<JobEvent> {
+        self.events_tx.subscribe()
+    }
+}
+
+#[tokio::test]
+async fn job_notifier_notify_subscribed() -> anyhow::Result<()> {
+    // arrange
+    let notifier = JobNotifier::new(8);
+    let mut rx = notifier.subscribe();
+
+    // act
+    notifier.notify(42);
+
+    // assert
+    assert_eq!(42, rx.recv().await?);
+    Ok(())
+}
@@ -0,0 +1,104 @@
+use std::{collections::HashMap, fmt::Debug};
+
+use axum::http::HeaderMap;
+
+/// Headers redacted unless `--redact-headers` is set, `*` suffix matches a prefix
+pub const DEFAULT_REDACT_HEADERS: &str =
+    "authorization,proxy-authorization,cookie,set-cookie,x-api-key,x-auth-token,irisqo-signature";
+/// Value shown instead of a redacted one
+pub const REDACTED: &str = "[redacted]";
+
+/// Headers whose values are hidden in API responses and logs
+#[derive(Debug, Clone)]
+pub struct Redaction {
+    names: Vec<String>,
+}
+
+impl Redaction {
+    /// Comma separated, case insensitive header names
+    pub fn new(list: &str) -> Self {
+        Self {
+            names: list
+                .split(',')
+                .map(|h| h.trim().to_ascii_lowercase())
+                .filter(|h| !h.is_empty())
+                .collect(),
+        }
+    }
+
+    pub fn is_sensitive(&self, name: &str) -> bool {
+        let name = name.to_ascii_lowercase();
+        self.names.iter().any(|r| match r.strip_suffix('*') {
+            Some(prefix) => name.starts_with(prefix),
+            None => *r == name,
+        })
+    }
+
+    pub fn redact(&self, headers: &mut Option<HashMap<String, String>>) {
+        for (name, value) in headers.iter_mut().flatten() {
+            if self.is_sensitive(name) {
+                *value = REDACTED.to_string();
+            }
+        }
+    }
+
+    /// `Debug` of `headers` with sensitive values redacted, for tracing
+    pub fn debug<'a>(&'a self, headers: &'a HeaderMap) -> RedactedHeaders<'a> {
+        RedactedHeaders {
+            headers,
+            redaction: self,
+        }
+    }
+}
+
+impl Default for Redaction {
+    fn default() -> Self {
+        Self::new(DEFAULT_REDACT_HEADERS)
+    }
+}
+
+pub struct RedactedHeaders<'a> {
+    headers: &'a HeaderMap,
+    redaction: &'a Redaction,
+}
+
+impl Debug for RedactedHeaders<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map()
+            .entries(self.headers.iter().map(|(name, value)| {
+                let value: &dyn Debug = match self.redaction.is_sensitive(name.as_str()) {
+                    true => &REDACTED,
+                    false => value,
+                };
+                (name, value)
+            }))
+            .finish()
+    }
+}
+
+#[tokio::test]
+async fn redaction_redact() -> anyhow::Result<()> {
+    // arrange
+    let redaction = Redaction::new("Authorization,x-secret-*");
+    let mut headers = Some(HashMap::from([
+        ("authorization".to_string(), "Bearer t0k3n".to_string()),
+        ("X-Secret-Key".to_string(), "k".to_string()),
+        ("content-type".to_string(), "text/plain".to_string()),
+    ]));
+    let mut map = HeaderMap::new();
+    map.insert("set-cookie", "session=s3ss10n".parse()?);
+    map.insert("content-type", "text/plain".parse()?);
+
+    // act
+    redaction.redact(&mut headers);
+    let logged = format!("{:?}", Redaction::default().debug(&map));
+
+    // assert
+    let headers = headers.unwrap();
+    assert_eq!(REDACTED, headers["authorization"]);
+    assert_eq!(REDACTED, headers["X-Secret-Key"]);
+    assert_eq!("text/plain", headers["content-type"]);
+    assert!(!logged.contains("s3ss10n"));
+    assert!(logged.contains("text/plain"));
+    Ok(())
+}
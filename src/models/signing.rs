@@ -0,0 +1,56 @@
+use irisqo::signing;
+
+use super::util::Sensitive;
+
+/// Env variable of the key signing every outbound request
+pub const SIGNING_KEY_ENV: &str = "IRISQO_SIGNING_KEY";
+/// Env variable of the key that replaces `IRISQO_SIGNING_KEY`, both sign while it is set
+pub const NEXT_SIGNING_KEY_ENV: &str = "IRISQO_SIGNING_KEY_NEXT";
+
+/// Server keys of the `v2` signatures, see `irisqo::signing`
+#[derive(Debug, Clone, Default)]
+pub struct SigningKeys {
+    current: Option<Sensitive<String>>,
+    next: Option<Sensitive<String>>,
+}
+
+impl SigningKeys {
+    pub fn new(current: Option<String>, next: Option<String>) -> Self {
+        let non_empty = |key: Option<String>| key.filter(|k| !k.is_empty()).map(Sensitive);
+        Self {
+            current: non_empty(current),
+            next: non_empty(next),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(
+            std::env::var(SIGNING_KEY_ENV).ok(),
+            std::env::var(NEXT_SIGNING_KEY_ENV).ok(),
+        )
+    }
+
+    /// One signature per configured key, none without keys
+    pub fn sign(&self, timestamp: i64, method: &str, url: &str, body: &[u8]) -> Vec<String> {
+        [&self.current, &self.next]
+            .into_iter()
+            .flatten()
+            .map(|key| signing::sign_request(key, timestamp, method, url, body))
+            .collect()
+    }
+}
+
+#[tokio::test]
+async fn signing_keys_sign() -> anyhow::Result<()> {
+    // arrange
+    let keys = SigningKeys::new(Some("secret-key".to_string()), Some(String::new()));
+
+    // act
+    let signatures = keys.sign(1_700_000_000, "GET", "https://example.com/", b"");
+
+    // assert
+    assert_eq!(1, signatures.len());
+    assert!(SigningKeys::default().sign(0, "GET", "/", b"").is_empty());
+    assert!(!format!("{:?}", keys).contains("secret-key"));
+    Ok(())
+}
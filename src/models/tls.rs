@@ -0,0 +1,276 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Debug,
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use bytes::Bytes;
+use http_body_util::Full;
+use hyper::Uri;
+use hyper_util::{client::legacy::Client, rt::TokioExecutor};
+use native_tls::{Certificate, Identity, Protocol};
+use serde::Deserialize;
+
+use super::{
+    DestinationConnector, DestinationPolicy, Error, JobCreate, JobProtocol, ProxyRules,
+    connector::TlsProfileConnector, destination::HostRule, proxy::ProxyConnector,
+};
+
+pub type HttpClient = Client<TlsProfileConnector, Full<Bytes>>;
+
+/// Entry of the `--tls-profiles` file, a JSON object of profiles by name
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TlsProfileConfig {
+    /// Destinations using the profile, hosts, `*.` domains, addresses and CIDRs
+    #[serde(default)]
+    pub hosts: Vec<String>,
+    /// PEM certificate chain presented to the destination
+    pub client_cert: Option<PathBuf>,
+    /// PEM PKCS#8 private key of `client_cert`
+    pub client_key: Option<PathBuf>,
+    /// PEM root certificates trusted besides the system ones
+    #[serde(default)]
+    pub ca: Vec<PathBuf>,
+    /// `1.0`, `1.1`, `1.2` or `1.3`
+    pub min_tls: Option<String>,
+    /// Server name sent and verified instead of the url host
+    pub sni: Option<String>,
+}
+
+struct TlsProfile {
+    name: String,
+    hosts: Vec<HostRule>,
+    client: HttpClient,
+}
+
+/// Hyper clients of the default and every TLS profile, each with its own
+/// connection pool, all connecting through `DestinationConnector` or a proxy
+pub struct HttpClients {
+    default: HttpClient,
+    /// Sorted by name, the first with matching `hosts` wins
+    profiles: Vec<TlsProfile>,
+    proxies: Arc<ProxyRules>,
+}
+
+fn build_client(
+    policy: &Arc<DestinationPolicy>,
+    proxies: &Arc<ProxyRules>,
+    tls: native_tls::TlsConnector,
+    sni: Option<String>,
+) -> HttpClient {
+    let connector = TlsProfileConnector::new(
+        DestinationConnector::new(Arc::clone(policy)),
+        ProxyConnector::new(Arc::clone(proxies), Arc::clone(policy)),
+        tls,
+        sni,
+    );
+    Client::builder(TokioExecutor::new()).build(connector)
+}
+
+fn tls_connector(name: &str, config: &TlsProfileConfig) -> Result<native_tls::TlsConnector, Error> {
+    let err = |detail: String| Error::TlsProfile(format!("{}: {}", name, detail));
+    let read = |path: &Path| {
+        fs::read(path).map_err(|e| err(format!("unable to read {}, {}", path.display(), e)))
+    };
+    let mut builder = native_tls::TlsConnector::builder();
+    match (&config.client_cert, &config.client_key) {
+        (Some(cert), Some(key)) => {
+            let identity = Identity::from_pkcs8(&read(cert)?, &read(key)?)
+                .map_err(|e| err(format!("client_cert, {}", e)))?;
+            builder.identity(identity);
+        }
+        (None, None) => {}
+        _ => return Err(err("client_cert requires client_key".into())),
+    }
+    for path in &config.ca {
+        let certs = Certificate::stack_from_pem(&read(path)?)
+            .map_err(|e| err(format!("ca {}, {}", path.display(), e)))?;
+        for cert in certs {
+            builder.add_root_certificate(cert);
+        }
+    }
+    if let Some(min_tls) = &config.min_tls {
+        let protocol = match min_tls.as_str() {
+            "1.0" => Protocol::Tlsv10,
+            "1.1" => Protocol::Tlsv11,
+            "1.2" => Protocol::Tlsv12,
+            "1.3" => Protocol::Tlsv13,
+            _ => return Err(err("min_tls must be 1.0, 1.1, 1.2 or 1.3".into())),
+        };
+        builder.min_protocol_version(Some(protocol));
+    }
+    builder.build().map_err(|e| err(e.to_string()))
+}
+
+impl HttpClients {
+    pub fn new(
+        policy: Arc<DestinationPolicy>,
+        proxies: Arc<ProxyRules>,
+        configs: BTreeMap<String, TlsProfileConfig>,
+    ) -> Result<Self, Error> {
+        let default =
+            native_tls::TlsConnector::new().map_err(|e| Error::TlsProfile(e.to_string()))?;
+        let mut profiles = Vec::new();
+        for (name, config) in configs {
+            let hosts = config
+                .hosts
+                .iter()
+                .map(|host| HostRule::parse(host))
+                .collect::<Result<_, _>>()
+                .map_err(|_| Error::TlsProfile(format!("{}: invalid hosts", name)))?;
+            let tls = tls_connector(&name, &config)?;
+            profiles.push(TlsProfile {
+                client: build_client(&policy, &proxies, tls, config.sni),
+                name,
+                hosts,
+            });
+        }
+        Ok(Self {
+            default: build_client(&policy, &proxies, default, None),
+            profiles,
+            proxies,
+        })
+    }
+
+    /// Profiles of a `--tls-profiles` JSON file, only the default client without one
+    pub fn load(
+        policy: Arc<DestinationPolicy>,
+        proxies: Arc<ProxyRules>,
+        path: Option<&Path>,
+    ) -> Result<Self, Error> {
+        let configs = match path {
+            None => BTreeMap::new(),
+            Some(path) => {
+                let json = fs::read(path).map_err(|e| {
+                    Error::TlsProfile(format!("unable to read {}, {}", path.display(), e))
+                })?;
+                serde_json::from_slice(&json).map_err(|e| Error::TlsProfile(e.to_string()))?
+            }
+        };
+        Self::new(policy, proxies, configs)
+    }
+
+    /// A profile is only used for the destinations in its `hosts`, so `_tls`
+    /// cannot present its client certificate anywhere else
+    fn profile(&self, name: &str, host: &str) -> Result<&TlsProfile, Error> {
+        let profile = self
+            .profiles
+            .iter()
+            .find(|profile| profile.name == name)
+            .ok_or_else(|| Error::TlsProfile(format!("unknown profile {}", name)))?;
+        match profile.hosts.iter().any(|rule| rule.matches_host(host)) {
+            true => Ok(profile),
+            false => Err(Error::TlsProfile(format!(
+                "{} is not allowed for {}",
+                name, host
+            ))),
+        }
+    }
+
+    /// `_tls` of a job names a configured profile of its destination
+    pub fn check_job(&self, job: &JobCreate) -> Result<(), Error> {
+        let host = match &job.meta.protocol {
+            JobProtocol::Http(http) => http.url.host().unwrap_or_default(),
+            JobProtocol::None => "",
+        };
+        match &job.meta.tls {
+            Some(name) => self.profile(name, host).map(|_| ()),
+            None => Ok(()),
+        }
+    }
+
+    /// Client of the `_tls` profile, else of the first profile matching the host
+    pub fn client(&self, profile: Option<&str>, uri: &Uri) -> Result<&HttpClient, Error> {
+        let host = uri.host().unwrap_or_default();
+        if let Some(name) = profile {
+            return self.profile(name, host).map(|profile| &profile.client);
+        }
+        Ok(self
+            .profiles
+            .iter()
+            .find(|profile| profile.hosts.iter().any(|rule| rule.matches_host(host)))
+            .map_or(&self.default, |profile| &profile.client))
+    }
+
+    /// `Proxy-Authorization` of an `http` destination sent to a proxy
+    pub fn proxy_authorization(&self, uri: &Uri) -> Option<&str> {
+        self.proxies.http_authorization(uri)
+    }
+
+    #[cfg(test)]
+    fn profile_name(&self, profile: Option<&str>, uri: &Uri) -> Option<&str> {
+        let client = self.client(profile, uri).ok()?;
+        self.profiles
+            .iter()
+            .find(|profile| std::ptr::eq(&profile.client, client))
+            .map(|profile| profile.name.as_str())
+    }
+}
+
+impl Debug for HttpClients {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HttpClients")
+            .field(
+                "profiles",
+                &self.profiles.iter().map(|p| &p.name).collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+#[tokio::test]
+async fn http_clients_select_profile() -> anyhow::Result<()> {
+    // arrange
+    let policy = Arc::new(DestinationPolicy::new("", "", "", "", true)?);
+    let configs: BTreeMap<String, TlsProfileConfig> = serde_json::from_value(serde_json::json!({
+        "partner": { "hosts": ["api.partner.com", "*.partner.net"], "min_tls": "1.2" },
+        "internal": { "hosts": ["10.0.0.0/8"], "sni": "billing.internal" }
+    }))?;
+    let proxies = Arc::new(ProxyRules::default());
+    let clients = HttpClients::new(policy.clone(), proxies.clone(), configs)?;
+    let uri = |s: &str| s.parse::<Uri>().unwrap();
+
+    // act
+    let by_host = clients.profile_name(None, &uri("https://api.partner.com/hook"));
+    let by_domain = clients.profile_name(None, &uri("https://eu.partner.net/hook"));
+    let by_net = clients.profile_name(None, &uri("https://10.1.2.3/hook"));
+    let by_job = clients.profile_name(Some("internal"), &uri("https://10.1.2.3/hook"));
+    let default = clients.profile_name(None, &uri("https://partner.net/hook"));
+
+    // assert
+    assert_eq!(Some("partner"), by_host);
+    assert_eq!(Some("partner"), by_domain);
+    assert_eq!(Some("internal"), by_net);
+    assert_eq!(Some("internal"), by_job);
+    assert_eq!(None, default);
+    assert!(
+        clients
+            .client(Some("nope"), &uri("https://example.com"))
+            .is_err()
+    );
+    assert!(
+        clients
+            .client(Some("partner"), &uri("https://attacker.example/hook"))
+            .is_err()
+    );
+    let bad_tls = BTreeMap::from([(
+        "old".to_string(),
+        TlsProfileConfig {
+            min_tls: Some("1.4".into()),
+            ..Default::default()
+        },
+    )]);
+    assert!(HttpClients::new(policy.clone(), proxies.clone(), bad_tls).is_err());
+    let half_identity = BTreeMap::from([(
+        "mtls".to_string(),
+        TlsProfileConfig {
+            client_cert: Some("/nonexistent.pem".into()),
+            ..Default::default()
+        },
+    )]);
+    assert!(HttpClients::new(policy, proxies, half_identity).is_err());
+    Ok(())
+}
@@ -0,0 +1,29 @@
+use std::{fmt::Debug, ops::Deref};
+
+use super::redact::REDACTED;
+
+/// Error of connectors and their futures
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Trimmed items of a comma separated list, empty ones are skipped
+pub fn split(list: &str) -> impl Iterator<Item = &str> {
+    list.split(',').map(str::trim).filter(|s| !s.is_empty())
+}
+
+/// Keys and credentials, `Debug` shows `[redacted]` so they are never logged
+#[derive(Clone, PartialEq)]
+pub struct Sensitive<T>(pub T);
+
+impl<T> Deref for Sensitive<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> Debug for Sensitive<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
@@ -0,0 +1,42 @@
+use std::sync::OnceLock;
+
+use opentelemetry::{KeyValue, global, metrics::Counter};
+
+use crate::models::BodyCodec;
+
+struct BodyMetrics {
+    raw_bytes: Counter<u64>,
+    saved_bytes: Counter<u64>,
+}
+
+fn body_metrics() -> &'static BodyMetrics {
+    static METRICS: OnceLock<BodyMetrics> = OnceLock::new();
+    METRICS.get_or_init(|| {
+        let meter = global::meter("irisqo");
+        BodyMetrics {
+            raw_bytes: meter
+                .u64_counter("irisqo.body.raw_bytes")
+                .with_description("Size of compressible bodies before compression")
+                .with_unit("By")
+                .build(),
+            saved_bytes: meter
+                .u64_counter("irisqo.body.saved_bytes")
+                .with_description("Bytes saved by compression of stored bodies")
+                .with_unit("By")
+                .build(),
+        }
+    })
+}
+
+/// `kind` is `request` or `response`
+pub fn record_body_compression(codec: BodyCodec, kind: &'static str, raw: usize, stored: usize) {
+    let metrics = body_metrics();
+    let attributes = [
+        KeyValue::new("codec", codec.to_string()),
+        KeyValue::new("kind", kind),
+    ];
+    metrics.raw_bytes.add(raw as u64, &attributes);
+    metrics
+        .saved_bytes
+        .add(raw.saturating_sub(stored) as u64, &attributes);
+}
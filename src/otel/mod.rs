@@ -1,4 +1,5 @@
 mod headerinjector;
+mod metrics;
 use std::borrow::Cow;
 
 use axum::extract::MatchedPath;
@@ -8,6 +9,7 @@ pub use headerinjector::HeaderExtractor;
 pub use headerinjector::HeaderInjector;
 use hyper::Uri;
 use hyper::Version;
+pub use metrics::record_body_compression;
 use opentelemetry::Context;
 use tower_http::classify::ServerErrorsFailureClass;
 use tracing::field::Empty;
@@ -0,0 +1,69 @@
+use tokio::{select, time};
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+
+use crate::{
+    db,
+    models::{AppState, Error},
+};
+use std::{sync::Arc, time::Duration};
+
+/// Interval between collections
+const GC_INTERVAL: Duration = Duration::from_secs(60 * 60);
+/// Blobs written or reused more recently may not be referenced yet
+const GC_GRACE: Duration = Duration::from_secs(60 * 60);
+/// Keys checked per query
+const BATCH_SIZE: usize = 500;
+
+/// Deletes offloaded bodies no longer referenced by a job or a result
+#[derive(Debug)]
+pub struct BlobGcService {
+    app_state: Arc<AppState>,
+}
+
+impl BlobGcService {
+    pub fn new(app_state: Arc<AppState>) -> Self {
+        Self { app_state }
+    }
+
+    pub async fn run(&self) -> Result<(), Error> {
+        let instance_id = &self.app_state.instance_id;
+        if self.app_state.body_options.blob_store.is_none() {
+            return Ok(());
+        }
+        info!({ instance_id }, "start");
+        let mut interval = time::interval(GC_INTERVAL);
+        interval.set_missed_tick_behavior(time::MissedTickBehavior::Skip);
+        while !self.app_state.shutdown_token.is_cancelled() {
+            select!(
+                biased;
+                _ = self.app_state.shutdown_token.cancelled() => {}
+                _ = interval.tick() => {
+                    match self.collect().await {
+                        Ok(deleted) => debug!({ instance_id, deleted }, "collect"),
+                        Err(err) => error!({ instance_id }, "collect error {:?}", err),
+                    }
+                },
+            );
+        }
+        info!({ instance_id }, "stop");
+        Ok(())
+    }
+
+    async fn collect(&self) -> Result<usize, Error> {
+        let Some(store) = &self.app_state.body_options.blob_store else {
+            return Ok(0);
+        };
+        let keys = store.keys_older_than(GC_GRACE).await?;
+        let mut deleted = 0;
+        for keys in keys.chunks(BATCH_SIZE) {
+            let referenced = db::blobs::referenced(&self.app_state.pool, keys).await?;
+            for key in keys.iter().filter(|key| !referenced.contains(key)) {
+                if store.delete_older_than(key, GC_GRACE).await? {
+                    deleted += 1;
+                }
+            }
+        }
+        Ok(deleted)
+    }
+}
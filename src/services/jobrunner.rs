@@ -4,11 +4,14 @@ use crate::{
         self,
         results::{self, JobResult},
         schedules::JobSchedule,
+        secrets, subscriptions,
     },
-    models::{AppState, Error, JobEntry, JobMeta, JobProtocol, JobRow, JobWithRetry},
+    models::{AppState, Capture, Error, JobEntry, JobMeta, JobProtocol, JobRow, JobWithRetry},
 };
-use bytes::Bytes;
+use bytes::{Bytes, BytesMut};
 use http_body_util::{BodyExt, Full};
+use hyper::body::Incoming;
+use irisqo::signing;
 #[allow(unused_imports)]
 use opentelemetry::{global, trace::TraceContextExt};
 use std::{collections::HashMap, time::Duration};
@@ -20,11 +23,11 @@ use tracing::{debug, error, info, warn};
 pub async fn job_get_and_run(app_state: &AppState, entry: JobEntry) {
     let instance_id = &app_state.instance_id;
     let JobEntry { id: job_id, retry } = entry;
-    let job = db::jobqueue::get_by_id(&app_state.pool, job_id)
+    let job = db::jobqueue::get_to_run(&app_state.pool, job_id)
         .await
         .and_then(|o| o.ok_or(Error::JobNotFound(job_id)))
         .map_err(|err| {
-            error!({ instance_id, job_id, retry }, "db::jobqueue::get_by_id error {:?}", err);
+            error!({ instance_id, job_id, retry }, "db::jobqueue::get_to_run error {:?}", err);
         });
     if let Ok(job) = job {
         let job_with_retry = JobWithRetry { job, retry };
@@ -53,29 +56,74 @@ async fn job_run_with_error(
     debug!({ instance_id, job_id, retry }, "==> run");
     let meta = job.meta.clone();
     let schedule_id = job.schedule_id.clone();
+    let tenant_id = job.tenant_id.clone();
     let job_result = match meta.protocol {
         JobProtocol::None => Ok(JobResult::default()),
         JobProtocol::Http(_) => job_run_http(app_state, job).await,
     };
     match job_result {
         Ok(result) => {
-            processed(app_state, job_id, schedule_id.as_deref(), result).await?;
+            processed(
+                app_state,
+                &tenant_id,
+                job_id,
+                schedule_id.as_deref(),
+                result,
+            )
+            .await?;
         }
         Err(err) => {
             if let Some(res) = on_error(app_state, JobEntry { id: job_id, retry }, meta, err).await
             {
                 warn!({ instance_id, job_id }, "====> job failed={:?}", &res.meta);
-                processed(app_state, job_id, schedule_id.as_deref(), res).await?;
+                processed(app_state, &tenant_id, job_id, schedule_id.as_deref(), res).await?;
             }
         }
     }
     Ok(())
 }
 
-async fn job_run_http(app_state: &AppState, job: JobRow) -> Result<JobResult, Error> {
+async fn job_run_http(app_state: &AppState, mut job: JobRow) -> Result<JobResult, Error> {
     let job_id = job.id;
+    app_state
+        .body_options
+        .decode(
+            &mut job.body,
+            &mut job.headers,
+            job.body_ref.as_deref(),
+            &mut job.body_codec,
+            &mut job.envelope,
+        )
+        .await?;
+    let host = match &job.meta.protocol {
+        JobProtocol::Http(http) => http.url.host().unwrap_or_default().to_string(),
+        _ => String::new(),
+    };
+    secrets::resolve_headers(app_state, &job.tenant_id, &host, &mut job.headers).await?;
     let timeout_ms = job.meta.timeout;
-    let req = hyper::Request::<Full<Bytes>>::try_from(job)?;
+    let capture = job.meta.capture.unwrap_or(app_state.worker_options.capture);
+    let max_response = app_state.body_options.max_response;
+    let capture_max = job
+        .meta
+        .capture_max
+        .or(app_state.worker_options.capture_max)
+        .map_or(max_response, |max| (max as usize).min(max_response));
+    let request_bytes = job.body.as_ref().map_or(0, Vec::len);
+    let signature = signature(app_state, &job).await?;
+    let tls = job.meta.tls.take();
+    let mut req = hyper::Request::<Full<Bytes>>::try_from(job)?;
+    if let Some(signature) = signature {
+        req.headers_mut().insert(
+            signing::SIGNATURE_HEADER,
+            signature.parse().map_err(hyper::http::Error::from)?,
+        );
+    }
+    if let Some(authorization) = app_state.clients.proxy_authorization(req.uri()) {
+        req.headers_mut().insert(
+            hyper::header::PROXY_AUTHORIZATION,
+            authorization.parse().map_err(hyper::http::Error::from)?,
+        );
+    }
     // OpenTelemetry
     // let tracer = global::tracer("irisqo");
     // let span = opentelemetry::trace::Tracer::span_builder(&tracer, String::from("job_run_http"))
@@ -87,26 +135,50 @@ async fn job_run_http(app_state: &AppState, job: JobRow) -> Result<JobResult, Er
     //     propagator.inject_context(&cx, &mut crate::otel::HeaderInjector(req.headers_mut()))
     // });
     // Call
-    let future = app_state.client.request(req);
+    let future = app_state
+        .clients
+        .client(tls.as_deref(), req.uri())?
+        .request(req);
     // first '?' - timeout
     // second '?' - HyperError
     let response = time::timeout(Duration::from_millis(timeout_ms.into()), future).await??;
-    debug!({ instance_id = app_state.instance_id, job_id }, "====> response={:?}", response);
+    debug!(
+        { instance_id = app_state.instance_id, job_id },
+        "====> response status={} version={:?} headers={:?}",
+        response.status(),
+        response.version(),
+        app_state.redaction.debug(response.headers())
+    );
     // StatusCode
     let status_code: hyper::StatusCode = response.status();
     let version: hyper::Version = response.version();
+    let capture = capture.resolve(status_code.is_client_error() || status_code.is_server_error());
     // Headers
-    let mut header_hashmap = HashMap::new();
-    for (k, v) in response.headers() {
-        if let Ok(val) = v.to_str() {
-            header_hashmap.insert(k.to_string(), val.to_owned());
+    let header_hashmap = capture.headers().then(|| {
+        let mut header_hashmap = HashMap::new();
+        for (k, v) in response.headers() {
+            if let Ok(val) = v.to_str() {
+                header_hashmap.insert(k.to_string(), val.to_owned());
+            }
         }
+        header_hashmap
+    });
+    // Body, not read when not captured
+    let (bytes, truncated) = match capture.body() {
+        true => collect_limited(response.into_body(), capture_max).await?,
+        false => (Bytes::new(), false),
+    };
+    if truncated {
+        debug!({ instance_id = app_state.instance_id, job_id }, "====> response body truncated");
     }
-    // Body
-    let collected = response.into_body().collect().await?;
-    let bytes = collected.to_bytes();
+    let response_bytes = bytes.len();
     // Result
-    let job_result = JobResult::http(status_code, version, Some(header_hashmap), bytes);
+    let mut job_result = match capture {
+        Capture::None => JobResult::default(),
+        _ => JobResult::http(status_code, version, header_hashmap, bytes, truncated),
+    };
+    job_result.meta.request_bytes = request_bytes.try_into().unwrap_or(u32::MAX);
+    job_result.meta.response_bytes = response_bytes.try_into().unwrap_or(u32::MAX);
     if status_code.is_server_error() {
         return Err(Error::ServerError(job_result));
     }
@@ -116,14 +188,83 @@ async fn job_run_http(app_state: &AppState, job: JobRow) -> Result<JobResult, Er
     Ok(job_result)
 }
 
+/// Reads at most `max` bytes, the rest of the body is dropped
+async fn collect_limited(mut body: Incoming, max: usize) -> Result<(Bytes, bool), Error> {
+    let mut buf = BytesMut::new();
+    while let Some(frame) = body.frame().await {
+        let Ok(data) = frame?.into_data() else {
+            continue;
+        };
+        let remaining = max - buf.len();
+        if data.len() > remaining {
+            buf.extend_from_slice(&data[..remaining]);
+            return Ok((buf.freeze(), true));
+        }
+        buf.extend_from_slice(&data);
+    }
+    Ok((buf.freeze(), false))
+}
+
+/// `irisqo-signature` of the request, with the secret of the subscription for
+/// deliveries and the signing keys of the server for every request
+async fn signature(app_state: &AppState, job: &JobRow) -> Result<Option<String>, Error> {
+    let timestamp = JobSchedule::now_secs();
+    let body = job.body.as_deref().unwrap_or_default();
+    let v1 = match &job.meta.subscription_id {
+        Some(subscription_id) => {
+            let subscription =
+                subscriptions::get_by_id(&app_state.pool, &job.tenant_id, subscription_id)
+                    .await?
+                    .ok_or(Error::InvalidParams("subscription"))?;
+            Some(signing::sign_body(&subscription.secret, timestamp, body))
+        }
+        None => None,
+    };
+    let v2 = match &job.meta.protocol {
+        JobProtocol::Http(http) => app_state.signing_keys.sign(
+            timestamp,
+            http.method.as_str(),
+            &http.url.to_string(),
+            body,
+        ),
+        JobProtocol::None => Vec::new(),
+    };
+    if v1.is_none() && v2.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(signing::header(timestamp, v1.as_deref(), &v2)))
+}
+
 async fn processed(
     app_state: &AppState,
+    tenant_id: &str,
     job_id: i64,
     schedule_id: Option<&str>,
-    result: JobResult,
+    mut result: JobResult,
 ) -> Result<(), Error> {
-    results::processed(&app_state.pool, job_id, result).await?;
-    let next_at = schedule_next_at(app_state, schedule_id).await;
+    // Keep the body and headers inline when the blob store fails, sealed when
+    // encryption is configured, and drop them when they cannot be sealed
+    let (mut body, mut headers) = (result.body.clone(), result.headers.clone());
+    let options = &app_state.body_options;
+    let encoding = match options.encode(&mut body, &mut headers, "response").await {
+        Ok(encoding) => {
+            (result.body, result.headers) = (body, headers);
+            encoding
+        }
+        Err(err) => {
+            error!({ instance_id = app_state.instance_id, job_id }, "encode error {:?}", err);
+            match options.encode_inline(&mut result.body, &mut result.headers, "response") {
+                Ok(encoding) => encoding,
+                Err(err) => {
+                    error!({ instance_id = app_state.instance_id, job_id }, "encode_inline error {:?}", err);
+                    (result.body, result.headers) = (Bytes::new(), None);
+                    Default::default()
+                }
+            }
+        }
+    };
+    results::processed(&app_state.pool, job_id, result, encoding).await?;
+    let next_at = schedule_next_at(app_state, tenant_id, schedule_id).await;
     if let Some(next_at) = next_at {
         let next_id = db::jobqueue::clone_schedule_at(
             &app_state.pool,
@@ -137,19 +278,18 @@ async fn processed(
     Ok(())
 }
 
-async fn schedule_next_at(app_state: &AppState, schedule_id: Option<&str>) -> Option<i64> {
+async fn schedule_next_at(
+    app_state: &AppState,
+    tenant_id: &str,
+    schedule_id: Option<&str>,
+) -> Option<i64> {
     let schedule_id = schedule_id?;
-
-    let row = features::schedules::get_by_id(&app_state.pool, schedule_id).await.map_err(|err| {
-        error!({ instance_id = app_state.instance_id, schedule_id }, "schedules::get_by_id error {:?}", err);
-    }).ok()??;
-    if row.inactive {
-        return None;
-    }
-    let schedule = row.schedule.parse::<JobSchedule>().map_err(|err| {
-        error!({ instance_id = app_state.instance_id, schedule_id }, "JobSchedule::parse error {:?}", err);
-    }).ok()?;
-    schedule.next(JobSchedule::now_secs(), row.until)
+    features::schedules::next_at(&app_state.pool, tenant_id, schedule_id, JobSchedule::now_secs())
+        .await
+        .map_err(|err| {
+            error!({ instance_id = app_state.instance_id, schedule_id }, "schedules::next_at error {:?}", err);
+        })
+        .ok()?
 }
 
 async fn on_error(
@@ -168,7 +308,8 @@ async fn on_error(
             if res.is_ok() {
                 return None;
             }
-            let job_result: JobResult = err.into();
+            let mut job_result: JobResult = err.into();
+            job_result.meta.dead_lettered = matches!(res, Err(Error::RetriesExceeded));
             Some(job_result)
         }
         _ => {
@@ -0,0 +1,70 @@
+use sqlx::postgres::PgListener;
+use tokio::{select, time};
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+
+use crate::models::{AppState, Error, HISTORY_CHANNEL, JobEvent, PROCESSED_CHANNEL};
+use std::sync::Arc;
+
+/// Forwards Postgres notifications into the in-process notifier
+#[derive(Debug)]
+pub struct ListenerService {
+    app_state: Arc<AppState>,
+}
+
+impl ListenerService {
+    pub fn new(app_state: Arc<AppState>) -> Self {
+        Self { app_state }
+    }
+
+    pub async fn run(&self) -> Result<(), Error> {
+        let instance_id = &self.app_state.instance_id;
+        info!({ instance_id }, "start");
+        while !self.app_state.shutdown_token.is_cancelled() {
+            if let Err(err) = self.listen().await {
+                error!({ instance_id }, "error {}", err);
+                select!(
+                    biased;
+                    _ = self.app_state.shutdown_token.cancelled() => {}
+                    _ = time::sleep(self.app_state.worker_options.poll_interval) => {},
+                );
+            }
+        }
+        info!({ instance_id }, "stop");
+        Ok(())
+    }
+
+    async fn listen(&self) -> Result<(), Error> {
+        let mut listener = PgListener::connect_with(&self.app_state.pool).await?;
+        listener
+            .listen_all([PROCESSED_CHANNEL, HISTORY_CHANNEL])
+            .await?;
+        loop {
+            select!(
+                biased;
+                _ = self.app_state.shutdown_token.cancelled() => return Ok(()),
+                notification = listener.recv() => {
+                    let notification = notification?;
+                    self.dispatch(notification.channel(), notification.payload());
+                },
+            );
+        }
+    }
+
+    fn dispatch(&self, channel: &str, payload: &str) {
+        let instance_id = &self.app_state.instance_id;
+        match channel {
+            PROCESSED_CHANNEL => {
+                if let Ok(job_id) = payload.parse::<i64>() {
+                    trace!({ instance_id, job_id }, "processed");
+                    self.app_state.notifier.notify(job_id);
+                }
+            }
+            HISTORY_CHANNEL => match serde_json::from_str::<JobEvent>(payload) {
+                Ok(event) => self.app_state.notifier.publish(event),
+                Err(err) => warn!({ instance_id }, "JobEvent parse error {:?}", err),
+            },
+            _ => {}
+        }
+    }
+}
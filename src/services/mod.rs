@@ -1,10 +1,14 @@
 #[cfg(feature = "batch-worker")]
 mod batchworkerservice;
+mod blobgcservice;
 mod channelworkerservice;
 pub mod jobrunner;
+mod listenerservice;
 #[cfg(feature = "naive-worker")]
 mod naiveworkerservice;
+pub mod reencrypt;
 mod schedulerservice;
+mod subscriptionservice;
 #[cfg(feature = "timer-worker")]
 mod timerjobservice;
 
@@ -26,6 +30,27 @@ pub async fn start_channel_worker_service(state: &Arc<AppState>) {
         .expect("Failed to run ChannelWorkerService");
 }
 
+pub async fn start_listener_service(state: &Arc<AppState>) {
+    let app_state = Arc::clone(state);
+    let service = listenerservice::ListenerService::new(app_state);
+    service.run().await.expect("Failed to run ListenerService");
+}
+
+pub async fn start_subscription_service(state: &Arc<AppState>) {
+    let app_state = Arc::clone(state);
+    let service = subscriptionservice::SubscriptionService::new(app_state);
+    service
+        .run()
+        .await
+        .expect("Failed to run SubscriptionService");
+}
+
+pub async fn start_blob_gc_service(state: &Arc<AppState>) {
+    let app_state = Arc::clone(state);
+    let service = blobgcservice::BlobGcService::new(app_state);
+    service.run().await.expect("Failed to run BlobGcService");
+}
+
 #[cfg(feature = "batch-worker")]
 pub async fn start_batch_jobs_service(state: &Arc<AppState>) {
     let app_state = Arc::clone(state);
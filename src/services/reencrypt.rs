@@ -0,0 +1,62 @@
+use tracing::info;
+
+use crate::{
+    db::encryption::{self, SealedRow, SealedTable},
+    features::secrets,
+    models::{AppState, Error},
+};
+
+/// Rows read per query
+const BATCH_SIZE: i64 = 500;
+
+/// Seals every row of `jobs` and `processed` with the current key, rows sealed by an
+/// older key only get their data key rewrapped, rows stored in plaintext are sealed
+pub async fn run(app_state: &AppState) -> Result<u64, Error> {
+    let keys = &app_state.body_options.encryption;
+    let key_id = keys
+        .current_id()
+        .ok_or(Error::Encryption("no encryption key"))?;
+    let mut total = 0;
+    for table in [SealedTable::Jobs, SealedTable::Processed] {
+        let mut after = 0;
+        let mut count = 0;
+        loop {
+            let rows =
+                encryption::get_to_reencrypt(&app_state.pool, table, key_id, after, BATCH_SIZE)
+                    .await?;
+            let Some(last) = rows.last() else {
+                break;
+            };
+            after = last.id;
+            for row in rows {
+                count += reencrypt_row(app_state, table, row).await?;
+            }
+        }
+        info!({ table = table.name(), key_id, count }, "re-encrypted rows");
+        total += count;
+    }
+    let count = secrets::rewrap_all(app_state, key_id).await?;
+    info!({ table = "secrets", key_id, count }, "re-encrypted rows");
+    Ok(total + count)
+}
+
+async fn reencrypt_row(
+    app_state: &AppState,
+    table: SealedTable,
+    mut row: SealedRow,
+) -> Result<u64, Error> {
+    let options = &app_state.body_options;
+    let previous = row.envelope.take();
+    row.envelope = match &previous {
+        Some(envelope) => options.encryption.rewrap(envelope)?,
+        None => {
+            options
+                .seal_stored(&mut row.body, &mut row.headers, &mut row.body_ref)
+                .await?
+        }
+    };
+    if row.envelope.is_none() {
+        return Ok(0);
+    }
+    encryption::update_sealed(&app_state.pool, table, &row, previous.as_ref()).await
+}
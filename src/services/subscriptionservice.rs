@@ -0,0 +1,65 @@
+use std::time::Duration;
+use tokio::{select, sync::broadcast::error::RecvError, time::sleep};
+#[allow(unused_imports)]
+use tracing::{debug, error, info, trace, warn};
+
+use crate::{
+    features::subscriptions,
+    models::{AppState, Error},
+};
+use std::sync::Arc;
+
+/// Interval of the outbox poll between events
+const OUTBOX_POLL: Duration = Duration::from_secs(5);
+
+/// Turns job lifecycle events into webhook delivery jobs
+#[derive(Debug)]
+pub struct SubscriptionService {
+    app_state: Arc<AppState>,
+}
+
+impl SubscriptionService {
+    pub fn new(app_state: Arc<AppState>) -> Self {
+        Self { app_state }
+    }
+
+    pub async fn run(&self) -> Result<(), Error> {
+        let instance_id = &self.app_state.instance_id;
+        info!({ instance_id }, "start");
+        let mut rx = self.app_state.notifier.subscribe_events();
+        loop {
+            self.drain().await;
+            // History events wake up the drain, the poll covers missed ones and other instances
+            select!(
+                biased;
+                _ = self.app_state.shutdown_token.cancelled() => break,
+                event = rx.recv() => match event {
+                    Ok(_) => {}
+                    Err(RecvError::Lagged(skipped)) => warn!({ instance_id, skipped }, "events lagged"),
+                    Err(RecvError::Closed) => break,
+                },
+                _ = sleep(OUTBOX_POLL) => {}
+            );
+        }
+        info!({ instance_id }, "stop");
+        Ok(())
+    }
+
+    /// Drains `subscription_outbox` until it is empty or fails
+    async fn drain(&self) {
+        let instance_id = &self.app_state.instance_id;
+        loop {
+            match subscriptions::deliver_outbox(&self.app_state).await {
+                Ok(0) => break,
+                Ok(drained) => debug!({ instance_id, drained }, "subscriptions::deliver_outbox"),
+                Err(err) => {
+                    error!(
+                        { instance_id },
+                        "subscriptions::deliver_outbox error {:?}", err
+                    );
+                    break;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,225 @@
+//! Signatures of the requests sent by irisqo, shared with the services receiving them.
+//!
+//! `irisqo-signature: t=<unix time>,v1=<hex>,v2=<hex>,v2=<hex>`
+//!
+//! - `v1` - HMAC-SHA256 of `"<t>.<body>"` with the secret of a subscription, webhook deliveries only
+//! - `v2` - HMAC-SHA256 of `"<t>.<METHOD>.<url>.<hex SHA-256 of body>"` with a signing key of the
+//!   server, once per key while a key is rotated
+//!
+//! ```
+//! use irisqo::signing;
+//!
+//! let (t, url) = (1_700_000_000, "https://example.com/a");
+//! let v2 = signing::sign_request("key", t, "POST", url, b"{}");
+//! let header = signing::header(t, None, &[v2]);
+//! let now = t + 60;
+//! let res = signing::verify(&header, "key", "POST", url, b"{}", now, signing::DEFAULT_TOLERANCE_SECS);
+//! assert!(res.is_ok());
+//! ```
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+
+type HmacSha256 = Hmac<Sha256>;
+
+pub const SIGNATURE_HEADER: &str = "irisqo-signature";
+
+/// Max age of a signature accepted by `verify`
+pub const DEFAULT_TOLERANCE_SECS: i64 = 5 * 60;
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum VerifyError {
+    #[error("Malformed signature header")]
+    Malformed,
+
+    #[error("Signature timestamp outside of tolerance")]
+    Expired,
+
+    #[error("No matching signature")]
+    Mismatch,
+}
+
+fn mac(key: &str) -> HmacSha256 {
+    HmacSha256::new_from_slice(key.as_bytes()).expect("HMAC can take key of any size")
+}
+
+fn mac_body(key: &str, timestamp: i64, body: &[u8]) -> HmacSha256 {
+    let mut mac = mac(key);
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    mac
+}
+
+fn mac_request(key: &str, timestamp: i64, method: &str, url: &str, body: &[u8]) -> HmacSha256 {
+    let mut mac = mac(key);
+    mac.update(format!("{}.{}.{}.", timestamp, method, url).as_bytes());
+    mac.update(hex::encode(Sha256::digest(body)).as_bytes());
+    mac
+}
+
+/// `v1` signature, hex
+pub fn sign_body(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    hex::encode(mac_body(secret, timestamp, body).finalize().into_bytes())
+}
+
+/// `v2` signature, hex
+pub fn sign_request(key: &str, timestamp: i64, method: &str, url: &str, body: &[u8]) -> String {
+    hex::encode(
+        mac_request(key, timestamp, method, url, body)
+            .finalize()
+            .into_bytes(),
+    )
+}
+
+/// Value of `SIGNATURE_HEADER`
+pub fn header(timestamp: i64, v1: Option<&str>, v2: &[String]) -> String {
+    let mut header = format!("t={}", timestamp);
+    if let Some(v1) = v1 {
+        header.push_str(",v1=");
+        header.push_str(v1);
+    }
+    for v2 in v2 {
+        header.push_str(",v2=");
+        header.push_str(v2);
+    }
+    header
+}
+
+/// Checks any `v1` or `v2` signature of `header` against `key`, `now` and the
+/// timestamp of the header may differ by `tolerance` seconds
+pub fn verify(
+    header: &str,
+    key: &str,
+    method: &str,
+    url: &str,
+    body: &[u8],
+    now: i64,
+    tolerance: i64,
+) -> Result<(), VerifyError> {
+    let mut timestamp: Option<i64> = None;
+    let mut signatures: Vec<(&str, Vec<u8>)> = Vec::new();
+    for part in header.split(',') {
+        let (name, value) = part.trim().split_once('=').ok_or(VerifyError::Malformed)?;
+        match name {
+            "t" => timestamp = Some(value.parse().map_err(|_| VerifyError::Malformed)?),
+            "v1" | "v2" => signatures.push((
+                name,
+                hex::decode(value).map_err(|_| VerifyError::Malformed)?,
+            )),
+            // Unknown schemes are left to newer versions
+            _ => {}
+        }
+    }
+    let timestamp = timestamp.ok_or(VerifyError::Malformed)?;
+    if (now - timestamp).abs() > tolerance {
+        return Err(VerifyError::Expired);
+    }
+    let matched = signatures.iter().any(|(scheme, signature)| {
+        let mac = match *scheme {
+            "v1" => mac_body(key, timestamp, body),
+            _ => mac_request(key, timestamp, method, url, body),
+        };
+        mac.verify_slice(signature).is_ok()
+    });
+    match matched {
+        true => Ok(()),
+        false => Err(VerifyError::Mismatch),
+    }
+}
+
+#[tokio::test]
+async fn verify_rotated_keys() -> anyhow::Result<()> {
+    // arrange
+    let (t, url, body) = (1_700_000_000, "https://example.com/hook?a=1", b"{}");
+    let header = header(
+        t,
+        None,
+        &[
+            sign_request("current", t, "POST", url, body),
+            sign_request("next", t, "POST", url, body),
+        ],
+    );
+
+    // act & assert
+    assert!(
+        verify(
+            &header,
+            "current",
+            "POST",
+            url,
+            body,
+            t + 10,
+            DEFAULT_TOLERANCE_SECS
+        )
+        .is_ok()
+    );
+    assert!(
+        verify(
+            &header,
+            "next",
+            "POST",
+            url,
+            body,
+            t + 10,
+            DEFAULT_TOLERANCE_SECS
+        )
+        .is_ok()
+    );
+    assert_eq!(
+        Err(VerifyError::Mismatch),
+        verify(&header, "old", "POST", url, body, t, DEFAULT_TOLERANCE_SECS)
+    );
+    assert_eq!(
+        Err(VerifyError::Mismatch),
+        verify(
+            &header,
+            "current",
+            "PUT",
+            url,
+            body,
+            t,
+            DEFAULT_TOLERANCE_SECS
+        )
+    );
+    assert_eq!(
+        Err(VerifyError::Expired),
+        verify(
+            &header,
+            "current",
+            "POST",
+            url,
+            body,
+            t + 600,
+            DEFAULT_TOLERANCE_SECS
+        )
+    );
+    assert_eq!(
+        Err(VerifyError::Malformed),
+        verify(
+            "v2=zz",
+            "current",
+            "POST",
+            url,
+            body,
+            t,
+            DEFAULT_TOLERANCE_SECS
+        )
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn verify_subscription_signature() -> anyhow::Result<()> {
+    // arrange
+    let secret = "whsec_test";
+    let v1 = sign_body(secret, 1_700_000_000, b"{}");
+
+    // act
+    let header = header(1_700_000_000, Some(&v1), &[]);
+
+    // assert
+    assert!(header.starts_with("t=1700000000,v1="));
+    assert_ne!(v1, sign_body(secret, 1_700_000_001, b"{}"));
+    assert!(verify(&header, secret, "POST", "", b"{}", 1_700_000_000, 0).is_ok());
+    Ok(())
+}